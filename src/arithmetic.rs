@@ -0,0 +1,718 @@
+//!Arbitrary-precision decimal arithmetic, for exact means
+//!
+//!Most decimal fractions have no exact binary form, so `0.1 + 0.2` is `0.30000000000000004`
+//!in floating point. A `Decimal` keeps every digit of the numbers as they were written:
+//!sums, differences and products are computed exactly, then rounded to the precision of a
+//!`Context` (34 significant digits by default), as are quotients.
+//!
+//!`evaluate` computes the mean value of an expression with this arithmetic. Only the values
+//!without a finite decimal form, such as `e`, `π`, square roots and non-integer powers,
+//!are computed in floating point.
+
+use crate::decimal::DecimalNumber;
+use crate::environment::Environment;
+use crate::error::{Error, Span};
+use crate::parser::Backend;
+use crate::statistics;
+use crate::token::Token;
+use crate::value::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+///Significant digits kept by the default context, as in IEEE 754 decimal128
+pub const DEFAULT_PRECISION: usize = 34;
+
+///Numbers which would be written with more zeros than this, before or after their digits,
+///are written in scientific notation instead
+const PLAIN_ZEROS: i64 = 20;
+
+///How a number is rounded to fewer digits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+    ///To the nearest, with ties to the even neighbour: 2.5 → 2 and 3.5 → 4
+    #[default]
+    HalfEven,
+    ///To the nearest, with ties away from zero: 2.5 → 3 and -2.5 → -3
+    HalfUp,
+    ///Drops the extra digits: 2.9 → 2 and -2.9 → -2
+    TowardZero,
+}
+
+///Precision and rounding of the results of decimal arithmetic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Context {
+    pub precision: usize, //significant digits of the results
+    pub rounding: Rounding,
+}
+
+impl Context {
+    ///Panics if the precision is zero
+    pub fn new(precision: usize, rounding: Rounding) -> Context {
+        if precision == 0 {
+            panic!("the precision must be at least one significant digit");
+        }
+        Context { precision, rounding }
+    }
+
+    ///Rounds the number to the precision of the context
+    pub fn round(&self, x: &Decimal) -> Decimal {
+        x.round_sig_figs(self.precision, self.rounding)
+    }
+}
+
+///34 significant digits, rounded half to even
+impl Default for Context {
+    fn default() -> Context {
+        Context::new(DEFAULT_PRECISION, Rounding::HalfEven)
+    }
+}
+
+///A signed decimal number with any number of digits, stored as `digits × 10^exponent`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Decimal {
+    negative: bool,
+    digits: Vec<u8>, //Most significant first, without leading or trailing zeros. Empty for zero.
+    exponent: i64,   //Power of ten of the last digit
+}
+
+impl Decimal {
+    ///Builds a number from its digits, removing their leading and trailing zeros
+    fn from_parts(negative: bool, mut digits: Vec<u8>, mut exponent: i64) -> Decimal {
+        let leading = digits.iter().take_while(|&&d| d == 0).count();
+        digits.drain(..leading);
+        while digits.last() == Some(&0) {
+            digits.pop();
+            exponent += 1;
+        }
+        if digits.is_empty() {
+            return Decimal::zero();
+        }
+        Decimal { negative, digits, exponent }
+    }
+
+    pub fn zero() -> Decimal {
+        Decimal::default()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    ///The shortest decimal form which gives back the float, e.g. `0.1` for 0.1,
+    ///or none if it is infinite or NaN
+    pub fn from_f64(x: f64) -> Option<Decimal> {
+        if !x.is_finite() {
+            return None;
+        }
+        format!("{:e}", x).parse().ok()
+    }
+
+    ///The nearest float
+    pub fn to_f64(&self) -> f64 {
+        if self.is_zero() {
+            return 0.0;
+        }
+        let sign = if self.negative { "-" } else { "" };
+        format!("{}{}e{}", sign, digit_string(&self.digits), self.exponent).parse().unwrap_or(f64::NAN)
+    }
+
+    ///The number as an integer, if it is one and fits in an `i32`
+    fn to_i32(&self) -> Option<i32> {
+        if self.exponent < 0 || self.magnitude() > 10 {
+            return None;
+        }
+        self.to_string().parse().ok()
+    }
+
+    ///Power of ten of the leading digit, e.g. 2 for 123.4
+    pub(crate) fn magnitude(&self) -> i64 {
+        self.exponent + self.digits.len() as i64 - 1
+    }
+
+    ///Rounds to `n` significant figures
+    pub fn round_sig_figs(&self, n: usize, rounding: Rounding) -> Decimal {
+        if self.is_zero() {
+            return Decimal::zero();
+        }
+        self.round_at(self.magnitude() - n as i64 + 1, rounding, false)
+    }
+
+    ///Rounds to the decimal place, counted from the decimal point: 2 for hundredths,
+    ///-1 for tens, etc.
+    pub fn round_to(&self, place: i32, rounding: Rounding) -> Decimal {
+        self.round_at(-(place as i64), rounding, false)
+    }
+
+    ///Rounds so that the last digit kept is at the power of ten `power`. `sticky` tells
+    ///whether non-zero digits were already dropped below those of the number.
+    fn round_at(&self, power: i64, rounding: Rounding, sticky: bool) -> Decimal {
+        if self.exponent >= power {
+            return self.clone();
+        }
+        let dropped = (power - self.exponent) as usize;
+        let kept = self.digits.len().saturating_sub(dropped);
+        let (first, rest) = if dropped <= self.digits.len() {
+            (self.digits[kept], &self.digits[kept + 1..])
+        } else {
+            (0, &self.digits[..])
+        };
+        let beyond_half = sticky || rest.iter().any(|&d| d != 0);
+        let mut digits = self.digits[..kept].to_vec();
+        let odd = digits.last().is_some_and(|d| d % 2 == 1);
+        let up = match rounding {
+            Rounding::HalfEven => first > 5 || (first == 5 && (beyond_half || odd)),
+            Rounding::HalfUp => first >= 5,
+            Rounding::TowardZero => false,
+        };
+        if up {
+            increment(&mut digits);
+        }
+        Decimal::from_parts(self.negative, digits, power)
+    }
+
+    ///Writes the number rounded to the decimal place (see `round_to`), with trailing
+    ///zeros up to that place, e.g. `2.5` is `2.50` at the second decimal, and `1.5e-25`
+    ///is `1.50e-25` at the 27th
+    pub fn to_places(&self, place: i32, rounding: Rounding) -> String {
+        let rounded = self.round_to(place, rounding);
+        let text = rounded.to_string();
+        match text.split_once('e') {
+            //The mantissa has the digits down to the place
+            Some((mantissa, exponent)) => {
+                let decimals = (place as i64 + rounded.magnitude()).max(0) as usize;
+                format!("{}e{}", pad_decimals(mantissa, decimals), exponent)
+            }
+            None => pad_decimals(&text, place.max(0) as usize),
+        }
+    }
+
+    ///Writes the number rounded to `n` significant figures, keeping the trailing
+    ///zeros which are significant, e.g. `2` is `2.00` with three
+    pub fn to_sig_figs(&self, n: usize, rounding: Rounding) -> String {
+        self.to_places(self.sig_figs_place(n, rounding), rounding)
+    }
+
+    ///The decimal place (see `round_to`) of the last of `n` significant figures, once rounded:
+    ///1 for 2.54 with two, but 0 for 9.96 as it is rounded up to 10
+    pub fn sig_figs_place(&self, n: usize, rounding: Rounding) -> i32 {
+        let n = n.max(1) as i64;
+        let rounded = self.round_sig_figs(n as usize, rounding);
+        let magnitude = if rounded.is_zero() { 0 } else { rounded.magnitude() };
+        (n - 1 - magnitude) as i32
+    }
+
+    ///The sum, rounded to the precision of the context.
+    ///
+    ///Unlike `+`, it doesn't write out the zeros between numbers of very different magnitudes,
+    ///e.g. `1e2000000000` and `1`: digits far below the precision only tell which way to round,
+    ///so they are replaced by a single (sticky) digit.
+    pub fn sum(&self, other: &Decimal, context: &Context) -> Decimal {
+        //Below the last digit kept (and two guard digits), and below every digit of `x`,
+        //`y` only moves `x` towards the next number with a digit at `power`
+        let sticky = |x: &Decimal, y: &Decimal| {
+            let power = (x.magnitude() - context.precision as i64 - 2).min(x.exponent);
+            if !x.is_zero() && !y.is_zero() && y.magnitude() < power {
+                Decimal { negative: y.negative, digits: vec![1], exponent: power - 1 }
+            } else {
+                y.clone()
+            }
+        };
+        context.round(&(sticky(other, self) + sticky(self, other)))
+    }
+
+    ///The quotient, rounded to the precision of the context. None if `other` is zero.
+    pub fn div(&self, other: &Decimal, context: &Context) -> Option<Decimal> {
+        if other.is_zero() {
+            return None;
+        }
+        //Enough digits for the quotient to have one more than the precision
+        let shift = (context.precision + 1 + other.digits.len()).saturating_sub(self.digits.len());
+        let mut dividend = self.digits.clone();
+        dividend.resize(dividend.len() + shift, 0);
+        let (quotient, remainder) = divide_digits(&dividend, &other.digits);
+        let exponent = self.exponent - shift as i64 - other.exponent;
+        let quotient = Decimal::from_parts(self.negative != other.negative, quotient, exponent);
+        if quotient.is_zero() {
+            return Some(quotient);
+        }
+        let power = quotient.magnitude() - context.precision as i64 + 1;
+        Some(quotient.round_at(power, context.rounding, !remainder.is_empty()))
+    }
+
+    ///Raises the number to an integer power, rounded to the precision of the context.
+    ///None for a negative power of zero.
+    pub fn powi(&self, n: i32, context: &Context) -> Option<Decimal> {
+        //The intermediate products keep a few more digits, so that mostly the result is rounded
+        let guard = Context::new(context.precision + 10, Rounding::HalfEven);
+        let mut result = Decimal::from(1);
+        let mut base = self.clone();
+        let mut k = n.unsigned_abs();
+        while k > 0 {
+            if k & 1 == 1 {
+                result = guard.round(&(result * base.clone()));
+            }
+            k >>= 1;
+            if k > 0 {
+                base = guard.round(&(base.clone() * base));
+            }
+        }
+        if n < 0 {
+            Decimal::from(1).div(&result, context)
+        } else {
+            Some(context.round(&result))
+        }
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(n: i64) -> Decimal {
+        let digits = n.unsigned_abs().to_string().bytes().map(|b| b - b'0').collect();
+        Decimal::from_parts(n < 0, digits, 0)
+    }
+}
+
+///The exact value of a number as written
+impl From<&DecimalNumber> for Decimal {
+    fn from(x: &DecimalNumber) -> Decimal {
+        //The digits of a decimal number are always valid
+        x.full_number().parse().unwrap_or_default()
+    }
+}
+
+///Reads a number such as `-12.5`, `.5`, `1e-3` or `6.022E23`
+impl FromStr for Decimal {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::unlocated(format!("invalid number: \"{}\"", text));
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().map_err(|_| invalid())?),
+            None => (unsigned, 0),
+        };
+        let (integral, fractional) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits: Vec<u8> = integral.bytes().chain(fractional.bytes()).map(|b| b.wrapping_sub(b'0')).collect();
+        if digits.is_empty() || digits.iter().any(|&d| d > 9) {
+            return Err(invalid());
+        }
+        Ok(Decimal::from_parts(negative, digits, exponent - fractional.len() as i64))
+    }
+}
+
+///Writes every digit, without an exponent, e.g. `0.0012` or `1500`, unless that takes
+///more than 20 zeros: `1e21` and `1.5e-25` are written in scientific notation
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        let sign = if self.negative { "-" } else { "" };
+        let digits = digit_string(&self.digits);
+        let point = self.digits.len() as i64 + self.exponent; //Digits before the decimal point
+        if self.exponent > PLAIN_ZEROS || point < -PLAIN_ZEROS {
+            let (first, rest) = digits.split_at(1);
+            let point = if rest.is_empty() { "" } else { "." };
+            write!(f, "{}{}{}{}e{}", sign, first, point, rest, self.magnitude())
+        } else if self.exponent >= 0 {
+            write!(f, "{}{}{}", sign, digits, "0".repeat(self.exponent as usize))
+        } else if point > 0 {
+            let (integral, fractional) = digits.split_at(point as usize);
+            write!(f, "{}{}.{}", sign, integral, fractional)
+        } else {
+            write!(f, "{}0.{}{}", sign, "0".repeat(-point as usize), digits)
+        }
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        let negative = !self.negative && !self.is_zero();
+        Decimal { negative, ..self }
+    }
+}
+
+impl Add for Decimal {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let (x, y, exponent) = align(&self, &other);
+        if self.negative == other.negative {
+            return Decimal::from_parts(self.negative, add_digits(&x, &y), exponent);
+        }
+        match compare(&x, &y) {
+            Ordering::Less => Decimal::from_parts(other.negative, subtract_digits(&y, &x), exponent),
+            _ => Decimal::from_parts(self.negative, subtract_digits(&x, &y), exponent),
+        }
+    }
+}
+
+impl Sub for Decimal {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl Mul for Decimal {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let digits = multiply_digits(&self.digits, &other.digits);
+        Decimal::from_parts(self.negative != other.negative, digits, self.exponent + other.exponent)
+    }
+}
+
+///Pads the fractional part of a number written without an exponent with zeros,
+///up to `decimals` digits
+fn pad_decimals(text: &str, decimals: usize) -> String {
+    match text.split_once('.') {
+        _ if decimals == 0 => text.to_string(),
+        Some((_, fractional)) => format!("{}{}", text, "0".repeat(decimals.saturating_sub(fractional.len()))),
+        None => format!("{}.{}", text, "0".repeat(decimals)),
+    }
+}
+
+fn digit_string(digits: &[u8]) -> String {
+    digits.iter().map(|&d| (b'0' + d) as char).collect()
+}
+
+///Adds one to the last digit, carrying over
+fn increment(digits: &mut Vec<u8>) {
+    for d in digits.iter_mut().rev() {
+        if *d == 9 {
+            *d = 0;
+        } else {
+            *d += 1;
+            return;
+        }
+    }
+    digits.insert(0, 1);
+}
+
+///The digits of both numbers, padded with zeros to a common exponent
+fn align(x: &Decimal, y: &Decimal) -> (Vec<u8>, Vec<u8>, i64) {
+    let exponent = x.exponent.min(y.exponent);
+    let pad = |d: &Decimal| {
+        let mut digits = d.digits.clone();
+        if !d.is_zero() {
+            digits.resize(digits.len() + (d.exponent - exponent) as usize, 0);
+        }
+        digits
+    };
+    (pad(x), pad(y), exponent)
+}
+
+///Compares two integers written without leading zeros
+fn compare(x: &[u8], y: &[u8]) -> Ordering {
+    x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+}
+
+fn add_digits(x: &[u8], y: &[u8]) -> Vec<u8> {
+    let mut sum = Vec::with_capacity(x.len().max(y.len()) + 1);
+    let (mut i, mut j, mut carry) = (x.len(), y.len(), 0);
+    while i > 0 || j > 0 || carry > 0 {
+        let mut digit = carry;
+        if i > 0 {
+            i -= 1;
+            digit += x[i];
+        }
+        if j > 0 {
+            j -= 1;
+            digit += y[j];
+        }
+        sum.push(digit % 10);
+        carry = digit / 10;
+    }
+    sum.reverse();
+    sum
+}
+
+///Subtracts `y` from `x`, which must be at least as large. The difference has no leading zeros.
+fn subtract_digits(x: &[u8], y: &[u8]) -> Vec<u8> {
+    let mut difference = Vec::with_capacity(x.len());
+    let (mut j, mut borrow) = (y.len(), 0);
+    for &digit in x.iter().rev() {
+        let mut d = digit as i8 - borrow;
+        if j > 0 {
+            j -= 1;
+            d -= y[j] as i8;
+        }
+        borrow = if d < 0 { 1 } else { 0 };
+        difference.push((d + 10 * borrow) as u8);
+    }
+    while difference.last() == Some(&0) {
+        difference.pop();
+    }
+    difference.reverse();
+    difference
+}
+
+fn multiply_digits(x: &[u8], y: &[u8]) -> Vec<u8> {
+    if x.is_empty() || y.is_empty() {
+        return Vec::new();
+    }
+    //Sums of products at each power of ten, least significant first
+    let mut sums = vec![0u64; x.len() + y.len()];
+    for (i, &a) in x.iter().rev().enumerate() {
+        for (j, &b) in y.iter().rev().enumerate() {
+            sums[i + j] += (a * b) as u64;
+        }
+    }
+    let mut carry = 0;
+    let mut product: Vec<u8> = sums
+        .into_iter()
+        .map(|sum| {
+            let total = sum + carry;
+            carry = total / 10;
+            (total % 10) as u8
+        })
+        .collect();
+    product.reverse();
+    product
+}
+
+///Long division of integers, returning the quotient and the remainder (without leading zeros)
+fn divide_digits(x: &[u8], y: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut quotient = Vec::with_capacity(x.len());
+    let mut remainder: Vec<u8> = Vec::new();
+    for &digit in x {
+        if !remainder.is_empty() || digit != 0 {
+            remainder.push(digit);
+        }
+        let mut q = 0;
+        while compare(&remainder, y) != Ordering::Less {
+            remainder = subtract_digits(&remainder, y);
+            q += 1;
+        }
+        quotient.push(q);
+    }
+    (quotient, remainder)
+}
+
+///Evaluates the expression after the definitions, returning its value with the uncertainty
+///propagated as usual, along with its mean value computed with decimal arithmetic.
+///
+///This is the mean of first-order propagation, where every measurement `x ± u` stands for its
+///mean `x`. The definitions are pairs of a name and an expression, which can be used by the
+///following ones. Comparisons have no mean, and `wmean` is not supported.
+pub fn evaluate(definitions: &[(&str, &str)], expression: &str, context: &Context) -> Result<(Value, Decimal), Error> {
+    let mut environment = Environment::new();
+    let mut means = Means { variables: HashMap::new(), context };
+    for (name, input) in definitions {
+        let (_, mean) = environment.try_assign_with(name, input, &means)?;
+        means.variables.insert(name.to_string(), mean);
+    }
+    environment.try_eval_with(expression, &means)
+}
+
+///Evaluates the mean value of the expression with decimal arithmetic, after the definitions.
+///See `evaluate`.
+///
+///Example:
+///```
+///use scicalc_rs::arithmetic::{mean, Context};
+///
+///let x = mean(&[("a", "0.1 ± 0.01")], "a + 0.2", &Context::default()).unwrap();
+///assert_eq!("0.3", x.to_string());
+///```
+pub fn mean(definitions: &[(&str, &str)], expression: &str, context: &Context) -> Result<Decimal, Error> {
+    Ok(evaluate(definitions, expression, context)?.1)
+}
+
+///Decimal arithmetic as the backend of the evaluation, computing means
+struct Means<'a> {
+    variables: HashMap<String, Decimal>, //means of the definitions
+    context: &'a Context,
+}
+
+impl Backend for Means<'_> {
+    type Value = Decimal;
+
+    fn variable(&self, name: &str, span: Span) -> Result<Decimal, Error> {
+        match self.variables.get(name) {
+            Some(x) => Ok(x.clone()),
+            None => Err(Error::new(format!("unknown variable: \'{}\'", name), span)),
+        }
+    }
+
+    fn number(&self, x: &DecimalNumber, _: Span) -> Result<Decimal, Error> {
+        Ok(Decimal::from(x))
+    }
+
+    fn constant(&self, x: f64, span: Span) -> Result<Decimal, Error> {
+        float(x, span)
+    }
+
+    fn operation(&self, op: &Token, operands: &[Decimal], span: Span) -> Result<Decimal, Error> {
+        let context = self.context;
+        match (op, operands) {
+            (Token::Add, [x, y]) => Ok(x.sum(y, context)),
+            (Token::Minus, [x, y]) => Ok(x.sum(&-y.clone(), context)),
+            (Token::Minus, [x]) => Ok(-x.clone()),
+            (Token::Mul, [x, y]) => Ok(context.round(&(x.clone() * y.clone()))),
+            (Token::Div, [x, y]) => x.div(y, context).ok_or_else(|| Error::new("division by zero", span)),
+            (Token::Caret, [x, y]) => match y.to_i32() {
+                Some(n) => x.powi(n, context).ok_or_else(|| Error::new("division by zero", span)),
+                None => float(x.to_f64().powf(y.to_f64()), span),
+            },
+            _ => Err(Error::new(format!("bad sub-expressions of \'{}\'", op), span)),
+        }
+    }
+
+    fn function(&self, name: &str, arguments: &[Decimal], span: Span) -> Result<Decimal, Error> {
+        match (name, arguments) {
+            //The mean of a measurement, e.g. '12.3 ± 0.4 (stat) ± 0.2 (syst)'
+            ("±", [mean, _]) | ("rect", [mean, _]) | ("tri", [mean, _]) | ("ushape", [mean, _]) => Ok(mean.clone()),
+            ("mean", samples) => {
+                //The partial sums keep a few more digits, as in `powi`
+                let guard = Context::new(self.context.precision + 10, Rounding::HalfEven);
+                let sum = samples.iter().fold(Decimal::zero(), |sum, x| sum.sum(x, &guard));
+                Ok(sum.div(&Decimal::from(samples.len() as i64), self.context).unwrap_or_default())
+            }
+            ("stdev", samples) | ("sem", samples) => {
+                let samples: Vec<f64> = samples.iter().map(Decimal::to_f64).collect();
+                let deviation = if name == "stdev" { statistics::stdev(&samples) } else { statistics::sem(&samples) };
+                float(deviation, span)
+            }
+            ("compare", _) => Err(Error::new("a comparison has no mean value", span)),
+            ("wmean", _) => Err(Error::new("wmean is not supported with decimal arithmetic", span)),
+            _ => Err(Error::new(format!("bad arguments of \'{}\'", name), span)),
+        }
+    }
+}
+
+///Switches to floating point for a value without a finite decimal form, such as `π`
+fn float(x: f64, span: Span) -> Result<Decimal, Error> {
+    Decimal::from_f64(x).ok_or_else(|| Error::new("the result is not a finite number", span))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(text: &str) -> Decimal {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!("0.3", decimal("0.30").to_string());
+        assert_eq!("-1500", decimal("-1.5e3").to_string());
+        assert_eq!("0.0012", decimal("1.2E-3").to_string());
+        assert_eq!("0", decimal("-0.000").to_string());
+        assert_eq!("0.5", decimal(".5").to_string());
+        assert_eq!("78800", Decimal::from(&DecimalNumber::new("78800")).to_string());
+        assert_eq!("0.1", Decimal::from_f64(0.1).unwrap().to_string());
+        assert_eq!(0.1, decimal("0.1").to_f64());
+        for invalid in &["", "-", ".", "1.2.3", "1e", "1e2.5", "abc", "--1"] {
+            assert!(invalid.parse::<Decimal>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_exact_operations() {
+        assert_eq!(decimal("0.3"), decimal("0.1") + decimal("0.2"));
+        assert_eq!(decimal("-0.1"), decimal("0.1") - decimal("0.2"));
+        assert_eq!(decimal("1e20"), decimal("99999999999999999999") + decimal("1"));
+        assert_eq!(decimal("0.02"), decimal("0.1") * decimal("0.2"));
+        assert_eq!(decimal("-121932631112635269"), decimal("123456789") * decimal("-987654321"));
+        assert_eq!(Decimal::zero(), decimal("2.5") - decimal("2.50"));
+    }
+
+    #[test]
+    fn test_distant_sums() {
+        let context = Context::default();
+        assert_eq!(decimal("1e2000000000"), decimal("1e2000000000").sum(&decimal("1"), &context));
+        assert_eq!(decimal("1e100000000"), decimal("1").sum(&decimal("1e100000000"), &context));
+        let short = Context::new(3, Rounding::HalfEven);
+        //Just above and below the tie of 1.005
+        assert_eq!(decimal("1.01"), decimal("1.005").sum(&decimal("1e-900"), &short));
+        assert_eq!(decimal("1"), decimal("1.005").sum(&decimal("-1e-900"), &short));
+        assert_eq!(decimal("1"), decimal("1").sum(&decimal("-1e-900"), &short));
+        assert_eq!(decimal("0.999"), decimal("1").sum(&decimal("-1e-900"), &Context::new(3, Rounding::TowardZero)));
+        assert_eq!(decimal("1.000000000000000000000000000000000000000000001"), decimal("1").sum(&decimal("1e-45"), &Context::new(50, Rounding::HalfEven)));
+    }
+
+    #[test]
+    fn test_scientific_display() {
+        assert_eq!("100000000000000000000", decimal("1e20").to_string());
+        assert_eq!("1e21", decimal("1e21").to_string());
+        assert_eq!("-1.5e-25", decimal("-1.5e-25").to_string());
+        assert_eq!("1e100000000", decimal("1e100000000").to_string());
+        assert_eq!("1.50e-25", decimal("1.5e-25").to_places(27, Rounding::HalfEven));
+        assert_eq!("1.0e30", decimal("1e30").to_sig_figs(2, Rounding::HalfEven));
+        assert_eq!(Ok("1e2000000000".to_string()), mean(&[], "1e2000000000 + 1", &Context::default()).map(|x| x.to_string()));
+    }
+
+    #[test]
+    fn test_division() {
+        let context = Context::default();
+        assert_eq!(decimal("0.25"), decimal("1").div(&decimal("4"), &context).unwrap());
+        assert_eq!("0.3333333333333333333333333333333333", decimal("1").div(&decimal("3"), &context).unwrap().to_string());
+        assert_eq!("0.6666666666666666666666666666666667", decimal("2").div(&decimal("3"), &context).unwrap().to_string());
+        let short = Context::new(3, Rounding::TowardZero);
+        assert_eq!("0.666", decimal("2").div(&decimal("3"), &short).unwrap().to_string());
+        assert_eq!("-142", decimal("-1").div(&decimal("0.00703"), &short).unwrap().to_string());
+        assert!(decimal("1").div(&Decimal::zero(), &context).is_none());
+    }
+
+    #[test]
+    fn test_rounding() {
+        let round = |x: &str, rounding| decimal(x).round_sig_figs(2, rounding).to_string();
+        assert_eq!(("2.2", "2.3", "2.2"), (round("2.25", Rounding::HalfEven).as_str(), round("2.25", Rounding::HalfUp).as_str(), round("2.25", Rounding::TowardZero).as_str()));
+        assert_eq!("2.4", round("2.35", Rounding::HalfEven));
+        assert_eq!("2.3", round("2.2500001", Rounding::HalfEven));
+        assert_eq!("-2.3", round("-2.25", Rounding::HalfUp));
+        assert_eq!("10", round("9.96", Rounding::HalfEven));
+        assert_eq!("1200", decimal("1234.5").to_sig_figs(2, Rounding::HalfEven));
+        assert_eq!("2.00", decimal("2").to_sig_figs(3, Rounding::HalfEven));
+        assert_eq!("0.0", decimal("0.04").to_places(1, Rounding::HalfEven));
+        assert_eq!("3.10", decimal("3.1").to_places(2, Rounding::HalfEven));
+        assert_eq!((1, 0, -2), (
+            decimal("2.54").sig_figs_place(2, Rounding::HalfEven),
+            decimal("9.96").sig_figs_place(2, Rounding::HalfEven),
+            decimal("78846").sig_figs_place(3, Rounding::TowardZero)
+        ));
+    }
+
+    #[test]
+    fn test_powers() {
+        let context = Context::default();
+        assert_eq!(decimal("1.21"), decimal("1.1").powi(2, &context).unwrap());
+        assert_eq!(decimal("0.0625"), decimal("2").powi(-4, &context).unwrap());
+        assert_eq!(decimal("1"), decimal("0").powi(0, &context).unwrap());
+        assert!(decimal("0").powi(-1, &context).is_none());
+    }
+
+    #[test]
+    fn test_mean() {
+        let context = Context::default();
+        let mean = |expression| super::mean(&[], expression, &context).map(|x| x.to_string());
+        assert_eq!(Ok("0.3".to_string()), mean("0.1 + 0.2"));
+        assert_eq!(Ok("0.3".to_string()), mean("(0.1 ± 0.01) + 0.2 ± 0.02 (syst)"));
+        assert_eq!(Ok("1".to_string()), mean("1 / 4 * 4"));
+        assert_eq!(Ok("9.8075".to_string()), mean("mean(9.81, 9.79, [9.83, 9.80])"));
+        assert_eq!(Ok("1.4142135623730951".to_string()), mean("2 ^ 0.5"));
+        assert_eq!(Ok("0.001".to_string()), mean("10 ^ -3"));
+        assert_eq!(Ok("5.00".to_string()), super::mean(&[("r", "rect(5.00, 0.02)")], "r", &context).map(|x| x.to_sig_figs(3, Rounding::HalfEven)));
+        assert!(mean("1 / (2 - 2)").is_err());
+        assert!(mean("1 ± 0.1 ~= 2").is_err());
+        assert!(mean("x + 1").is_err());
+        let (value, mean) = evaluate(&[("a", "0.1 ± 0.01")], "a + 0.2", &context).unwrap();
+        assert_eq!("0.3", mean.to_string());
+        assert!(matches!(value, Value::Measurement(m) if m.sigma == 0.01));
+    }
+}
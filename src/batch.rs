@@ -0,0 +1,151 @@
+//!Evaluation of an expression for every row of a CSV table
+
+use crate::environment::Environment;
+use crate::error::Error;
+use crate::measurement::Measurement;
+use crate::value::Value;
+
+///Prefix of the columns holding the uncertainty of another column, e.g. `sigma_V` for `V`
+pub const SIGMA_PREFIX: &str = "sigma_";
+
+///Evaluates `expression` for every row of a CSV table, and returns the table with two
+///more columns: `name` and `sigma_<name>`, the mean and uncertainty of the result.
+///
+///The first line of the table holds the names of the columns, which become variables.
+///A column `X` is a measurement if there's also a column `sigma_X` with its uncertainty,
+///otherwise its values are exact numbers. The rows are evaluated independently of each
+///other, in a copy of `environment`, so that constants defined there can be used.
+///
+///Panics if the table or the expression are not valid, see `try_eval_csv`.
+pub fn eval_csv(environment: &Environment, csv: &str, name: &str, expression: &str) -> String {
+    match try_eval_csv(environment, csv, name, expression) {
+        Ok(table) => table,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+///Same as `eval_csv`, but returns the first error found in the table or in the expression
+pub fn try_eval_csv(environment: &Environment, csv: &str, name: &str, expression: &str) -> Result<String, Error> {
+    let mut lines = csv.lines().filter(|line| !line.trim().is_empty());
+    let header = match lines.next() {
+        Some(line) => split_row(line),
+        None => return Err(Error::unlocated("the CSV table is empty")),
+    };
+    let sigma_of = |column: &str| header.iter().position(|h| *h == format!("{}{}", SIGMA_PREFIX, column));
+
+    let mut output = String::new();
+    output += &join_row(header.iter().map(|h| h.as_str()).chain(vec![name, &format!("{}{}", SIGMA_PREFIX, name)]));
+    for (row, line) in lines.enumerate() {
+        let cells = split_row(line);
+        if cells.len() != header.len() {
+            let message = format!("row {} has {} columns, but the header has {}", row + 1, cells.len(), header.len());
+            return Err(Error::unlocated(message));
+        }
+        let number = |i: usize| -> Result<f64, Error> {
+            cells[i].trim().parse().map_err(|_| {
+                Error::unlocated(format!("row {}, column \'{}\': \'{}\' is not a number", row + 1, header[i], cells[i]))
+            })
+        };
+        let mut env = environment.clone();
+        for (i, column) in header.iter().enumerate() {
+            if column.starts_with(SIGMA_PREFIX) && header.contains(&column[SIGMA_PREFIX.len()..].to_string()) {
+                continue; //The uncertainty of another column
+            }
+            match sigma_of(column) {
+                Some(j) => env.define(column, Measurement::new(number(i)?, number(j)?)),
+                None => env.define(column, number(i)?),
+            }
+        }
+        let (mean, sigma) = match env.try_eval(expression)? {
+            Value::PosNumber(x) | Value::Number(x) => (x, 0.0),
+            Value::Rational(x) => (x.to_f64(), 0.0),
+            Value::Measurement(m) => (m.mean, m.sigma),
+            Value::Labeled(m) => (m.mean(), m.sigma()),
+            v => return Err(Error::unlocated(format!("row {}: the result is not a number: {}", row + 1, v))),
+        };
+        let (mean, sigma) = (mean.to_string(), sigma.to_string());
+        output += &join_row(cells.iter().map(|c| c.as_str()).chain(vec![mean.as_str(), sigma.as_str()]));
+    }
+    Ok(output)
+}
+
+///Splits a line of a CSV table into its cells. Cells may be quoted, e.g. `"a, b"`,
+///with `""` standing for a quote inside them.
+fn split_row(line: &str) -> Vec<String> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            },
+            '"' => quoted = !quoted,
+            ',' if !quoted => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            _ => cell.push(c),
+        }
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+///Joins cells into a line of a CSV table, quoting them if needed
+fn join_row<'a, I: Iterator<Item = &'a str>>(cells: I) -> String {
+    let cells: Vec<String> = cells
+        .map(|cell| {
+            if cell.contains(',') || cell.contains('"') {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.to_string()
+            }
+        })
+        .collect();
+    cells.join(",") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resistance() {
+        let csv = "V, sigma_V, I, sigma_I\n10, 0.1, 2, 0.02\n\n6, 0.3, 3, 0\n";
+        let table = eval_csv(&Environment::new(), csv, "R", "V / I");
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!("V,sigma_V,I,sigma_I,R,sigma_R", lines[0]);
+        let r = Measurement::new(10.0, 0.1) / Measurement::new(2.0, 0.02);
+        assert_eq!(format!("10,0.1,2,0.02,{},{}", r.mean, r.sigma), lines[1]);
+        assert_eq!("6,0.3,3,0,2,0.09999999999999999", lines[2]);
+        assert_eq!(3, lines.len());
+    }
+
+    #[test]
+    fn test_exact_columns_and_constants() {
+        let mut env = Environment::new();
+        env.define("k", 2.0);
+        let table = eval_csv(&env, "x,n\n1.5,2\n", "y", "k * x * n");
+        assert_eq!("x,n,y,sigma_y\n1.5,2,6,0\n", table);
+    }
+
+    #[test]
+    fn test_quoted_cells() {
+        assert_eq!(vec!["a, b", "say \"hi\"", "c"], split_row("\"a, b\",\"say \"\"hi\"\"\", c"));
+        assert_eq!("\"a, b\",c\n", join_row(vec!["a, b", "c"].into_iter()));
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = try_eval_csv(&Environment::new(), "x\nabc\n", "y", "2 * x").unwrap_err();
+        assert_eq!("row 1, column 'x': 'abc' is not a number", error.message);
+        let error = try_eval_csv(&Environment::new(), "x\n1\n", "y", "2 * z").unwrap_err();
+        assert_eq!("unknown variable: 'z'", error.message);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_missing_cell() {
+        eval_csv(&Environment::new(), "x,y\n1\n", "z", "x + y");
+    }
+}
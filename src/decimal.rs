@@ -1,23 +1,61 @@
+use crate::arithmetic::{Decimal, Rounding};
+use crate::error::{Error, Span};
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
 
 ///Stores the integral/integer and fractional parts of a number
 ///written in decimal representation as strings.
 ///
 ///For example: 3.14159265 has
 ///integral = '3' and fractional = '14159265'
+///
+///The trailing zeros of an integer such as `78800` are ambiguous: they may only place the
+///decimal point, or have been measured. A trailing period (`78800.`) or scientific
+///notation (`7.8800e4`) tells that they are significant.
+///
+///With the `serde` feature, it is serialized as a string with its
+///digits as written, e.g. `"3.14159265"`, `"78800"` or `"7.8800e4"`
 #[derive(Debug, Clone, PartialEq)]
 pub struct DecimalNumber {
     integral: String,
     fractional: String,
+    point: bool, //Written with a decimal point, e.g. '78800.' whose trailing zeros are significant
+    exponent: i32, //Power of ten of scientific notation, e.g. 4 for '7.8800e4'
+}
+
+///Whether the trailing zeros of an integer such as `78800` are significant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailingZeros {
+    ///They only place the decimal point: `78800` has 3 significant figures
+    #[default]
+    NotSignificant,
+    ///They were measured: `78800` has 5 significant figures
+    Significant,
 }
 
 impl DecimalNumber {
+    ///Reads a number such as `3.14`, `.5`, `788`, `78800.` or `7.8800e4`.
+    ///
+    ///Panics if the text is not a valid decimal number, see `from_str`.
     pub fn new(text: &str) -> DecimalNumber {
+        match text.parse() {
+            Ok(number) => number,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    ///Splits a mantissa which was already validated into the integral and fractional parts
+    fn split(text: &str, exponent: i32) -> DecimalNumber {
         let parts: Vec<&str> = text.split('.').collect();
         if parts.len() == 1 {
             DecimalNumber {
                 integral: parts[0].into(),
                 fractional: "".into(),
+                point: false,
+                exponent,
             }
         } else {
             DecimalNumber {
@@ -27,30 +65,131 @@ impl DecimalNumber {
                 }
                 .into(),
                 fractional: parts[1].into(),
+                point: true,
+                exponent,
             }
         }
     }
     pub fn full_number(&self) -> String {
-        if self.fractional == "" {
-            format!("{}", self.integral)
-        } else {
-            format!("{}.{}", self.integral, self.fractional)
+        let mut text = self.integral.clone();
+        if self.point {
+            text = format!("{}.{}", text, self.fractional);
+        }
+        if self.exponent != 0 {
+            text = format!("{}e{}", text, self.exponent);
         }
-        
+        text
     }
-    ///Returns the number of significant figures of the DecimalNumber
+    ///Returns the number of significant figures of the DecimalNumber,
+    ///assuming that the trailing zeros of an integer are not significant
     ///
     ///Examples:
     /// - "83.2" has 3 sig figs
     /// - "0.032" has 2 sig figs(note the preceding zeroes)
     /// - "788" has 3 sig figs
     /// - "78800" also has 3 sig figs(note the trailing zeroes)
+    /// - "78800." and "7.8800e4" have 5 sig figs
     /// - "78801" has 5 sig figs
     pub fn sig_figs(&self) -> usize {
-        sig_figs_helper(&self)
+        sig_figs_helper(self, TrailingZeros::NotSignificant)
+    }
+    ///Returns the number of significant figures, with the given assumption
+    ///about the trailing zeros of an integer
+    pub fn sig_figs_with(&self, zeros: TrailingZeros) -> usize {
+        sig_figs_helper(self, zeros)
+    }
+    ///Returns every possible number of significant figures, e.g. 3 to 5 for "78800"
+    pub fn sig_figs_range(&self) -> RangeInclusive<usize> {
+        self.sig_figs_with(TrailingZeros::NotSignificant)..=self.sig_figs_with(TrailingZeros::Significant)
+    }
+    ///Tells whether the number has trailing zeros which may or may not be significant,
+    ///such as those of "78800" but not those of "78800." or "7.8800e4"
+    pub fn is_ambiguous(&self) -> bool {
+        !self.point && self.integral.ends_with('0') && self.integral.trim_start_matches('0').len() > 1
+    }
+    ///Returns the decimal place of the last significant figure, counted
+    ///from the decimal point as in `Decimal::round_to`
+    ///
+    ///Examples:
+    /// - "3.14" has its last significant figure at place 2(hundredths)
+    /// - "788" at place 0(units)
+    /// - "78800" at place -2(hundreds), as its trailing zeroes are not significant
+    /// - "7.88e4" at place -2 too
+    pub fn decimal_place(&self) -> i32 {
+        let place = if self.point {
+            self.fractional.chars().count() as i32
+        } else {
+            match self.integral.trim_end_matches('0') {
+                "" => 0,
+                digits => -((self.integral.len() - digits.len()) as i32),
+            }
+        };
+        place - self.exponent
+    }
+    ///Rounds to `n` significant figures (at least one) in decimal, without going through
+    ///floating point. The result is written so that it has exactly `n` significant figures:
+    ///"78846" is "78800" with 3, and "79996" is "8.000e4" with 4.
+    pub fn round_sig_figs(&self, n: usize, rounding: Rounding) -> DecimalNumber {
+        self.round_to(Decimal::from(self).sig_figs_place(n, rounding), rounding)
+    }
+    ///Rounds to the decimal place, counted as in `decimal_place`: 2 for hundredths, -1 for tens,
+    ///etc. The result is written with its last significant figure at that place, with a trailing
+    ///period or in scientific notation if needed: "99.7" is "100." at place 0, and "79960" is
+    ///"8.00e4" at place -2.
+    pub fn round_to(&self, place: i32, rounding: Rounding) -> DecimalNumber {
+        let rounded = Decimal::from(self).round_to(place, rounding);
+        let number = DecimalNumber::new(&rounded.to_places(place, rounding));
+        if number.decimal_place() == place || rounded.is_zero() {
+            number
+        } else if place == 0 {
+            DecimalNumber { point: true, ..number }
+        } else {
+            let magnitude = rounded.magnitude() as i32;
+            let scale: Decimal = format!("1e{}", -magnitude).parse().unwrap_or_default();
+            let mantissa = (rounded * scale).to_places(magnitude + place, rounding);
+            DecimalNumber::new(&format!("{}e{}", mantissa, magnitude))
+        }
     }
     pub fn as_float(&self) -> f64 {
-        self.full_number().as_str().parse::<f64>().unwrap()
+        //The digits were validated on construction, so this is always a number
+        let fractional = if self.fractional.is_empty() { "0" } else { &self.fractional };
+        format!("{}.{}e{}", self.integral, fractional, self.exponent).parse::<f64>().unwrap_or(f64::NAN)
+    }
+}
+
+///Reads a positive number written in decimal, made of digits with at most one
+///period among them, e.g. `3.14`, `.5` or `78800.`, and optionally an exponent
+///such as `e4` or `E-3`. Signs are not part of a decimal number.
+impl FromStr for DecimalNumber {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (mantissa, exponent) = match text.find(['e', 'E']) {
+            Some(i) => (&text[..i], Some(&text[i + 1..])),
+            None => (text, None),
+        };
+        let mut found_period = false;
+        for (i, c) in mantissa.chars().enumerate() {
+            match c {
+                '0'..='9' => (),
+                '.' if !found_period => found_period = true,
+                _ => return Err(Error::new(format!("invalid decimal number: \"{}\"", text), Span::new(i, i + 1))),
+            }
+        }
+        if !mantissa.chars().any(|c| c.is_ascii_digit()) {
+            return Err(Error::unlocated(format!("invalid decimal number: \"{}\"", text)));
+        }
+        let exponent = match exponent {
+            None => 0,
+            Some(exponent) => match exponent.parse::<i32>() {
+                Ok(n) if exponent.ends_with(|c: char| c.is_ascii_digit()) => n,
+                _ => {
+                    let span = Span::new(mantissa.chars().count(), text.chars().count());
+                    return Err(Error::new(format!("invalid exponent: \"{}\"", text), span));
+                }
+            },
+        };
+        Ok(DecimalNumber::split(mantissa, exponent))
     }
 }
 
@@ -60,15 +199,30 @@ impl fmt::Display for DecimalNumber {
     }
 }
 
+#[cfg(feature = "serde")]
+impl Serialize for DecimalNumber {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.full_number())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for DecimalNumber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(|e: Error| de::Error::custom(e.message))
+    }
+}
+
 //TODO: use sig figs
+#[cfg(test)]
 fn sig_figs(number: &str) -> usize {
     let d = DecimalNumber::new(number);
-    sig_figs_helper(&d)
+    sig_figs_helper(&d, TrailingZeros::NotSignificant)
 }
 
 ///Does the character-by-character counting
 ///for the amount of significant figures in a decimal number
-fn sig_figs_helper(x: &DecimalNumber) -> usize {
+fn sig_figs_helper(x: &DecimalNumber, zeros: TrailingZeros) -> usize {
     let mut start_count = false;
     let mut counter: usize = 0;
     let mut backtracking = false;
@@ -90,8 +244,8 @@ fn sig_figs_helper(x: &DecimalNumber) -> usize {
         }
     }
 
-    if x.fractional.chars().count() == 0 {
-        return if backtracking {
+    if x.fractional.chars().count() == 0 && !x.point {
+        return if backtracking && zeros == TrailingZeros::NotSignificant {
             backtrack_value
         } else {
             counter
@@ -107,12 +261,12 @@ fn sig_figs_helper(x: &DecimalNumber) -> usize {
         }
     }
 
-    return counter;
+    counter
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{sig_figs, DecimalNumber};
+    use super::{sig_figs, DecimalNumber, Rounding, Span, TrailingZeros};
 
     #[test]
     fn test_decimal_number_1() {
@@ -146,6 +300,61 @@ mod tests {
         assert_eq!(2, d.sig_figs());
     }
 
+    #[test]
+    fn test_trailing_zeros() {
+        let d = DecimalNumber::new("78800");
+        assert!(d.is_ambiguous());
+        assert_eq!(3..=5, d.sig_figs_range());
+        assert_eq!(5, d.sig_figs_with(TrailingZeros::Significant));
+        assert_eq!(3, d.sig_figs_with(TrailingZeros::default()));
+        for explicit in &["78800.", "7.8800e4", "788.00e2"] {
+            let d = DecimalNumber::new(explicit);
+            assert!(!d.is_ambiguous());
+            assert_eq!(5..=5, d.sig_figs_range());
+        }
+        assert_eq!(3..=3, DecimalNumber::new("7.88e4").sig_figs_range());
+        assert!(DecimalNumber::new("10").is_ambiguous());
+        assert!(!DecimalNumber::new("0").is_ambiguous());
+        assert!(!DecimalNumber::new("788").is_ambiguous());
+        assert!(!DecimalNumber::new("780.0").is_ambiguous());
+    }
+
+    #[test]
+    fn test_decimal_place() {
+        assert_eq!(2, DecimalNumber::new("3.14").decimal_place());
+        assert_eq!(3, DecimalNumber::new("0.030").decimal_place());
+        assert_eq!(0, DecimalNumber::new("788").decimal_place());
+        assert_eq!(-2, DecimalNumber::new("78800").decimal_place());
+        assert_eq!(0, DecimalNumber::new("0").decimal_place());
+        assert_eq!(0, DecimalNumber::new("78800.").decimal_place());
+        assert_eq!(0, DecimalNumber::new("7.8800e4").decimal_place());
+        assert_eq!(-2, DecimalNumber::new("7.88e4").decimal_place());
+        assert_eq!(5, DecimalNumber::new("1.5e-4").decimal_place());
+    }
+
+    #[test]
+    fn test_rounding() {
+        let round = |x: &str, n, rounding| DecimalNumber::new(x).round_sig_figs(n, rounding).to_string();
+        assert_eq!("2.2", round("2.25", 2, Rounding::HalfEven));
+        assert_eq!("2.3", round("2.25", 2, Rounding::HalfUp));
+        assert_eq!("2.2", round("2.29", 2, Rounding::TowardZero));
+        assert_eq!("78800", round("78846", 3, Rounding::HalfEven));
+        assert_eq!("8.000e4", round("79996", 4, Rounding::HalfEven));
+        assert_eq!("10.", round("9.96", 2, Rounding::HalfEven));
+        assert_eq!("0.10", round("0.0996", 2, Rounding::HalfEven));
+        assert_eq!("0.00120", round("1.2e-3", 3, Rounding::HalfEven));
+        assert_eq!(4, DecimalNumber::new("79996").round_sig_figs(4, Rounding::HalfEven).sig_figs());
+
+        let round = |x: &str, place, rounding| DecimalNumber::new(x).round_to(place, rounding).to_string();
+        assert_eq!("3.14", round("3.14159", 2, Rounding::HalfEven));
+        assert_eq!("3.1415", round("3.14159", 4, Rounding::TowardZero));
+        assert_eq!("100.", round("99.7", 0, Rounding::HalfEven));
+        assert_eq!("78800", round("78846", -2, Rounding::HalfEven));
+        assert_eq!("8.00e4", round("79960", -2, Rounding::HalfUp));
+        assert_eq!("0.00", round("0.004", 2, Rounding::HalfEven));
+        assert_eq!("2.500", round("2.5", 3, Rounding::HalfEven));
+    }
+
     #[test]
     fn test_decimal_to_float() {
         let d = DecimalNumber::new("81");
@@ -155,4 +364,42 @@ mod tests {
         let d = DecimalNumber::new(".178");
         assert_eq!(0.178, d.as_float());
     }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let d: DecimalNumber = serde_json::from_str("\"78800\"").unwrap();
+        assert_eq!(DecimalNumber::new("78800"), d);
+        assert_eq!("\"0.095\"", serde_json::to_string(&DecimalNumber::new(".095")).unwrap());
+        assert_eq!("\"7.8800e4\"", serde_json::to_string(&DecimalNumber::new("7.8800e4")).unwrap());
+        for invalid in &["\"\"", "\"1.2.3\"", "\"-1\"", "\"1e\"", "\".\"", "1.5"] {
+            assert!(serde_json::from_str::<DecimalNumber>(invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(DecimalNumber::new("3.14"), "3.14".parse().unwrap());
+        assert_eq!(0.5, ".5".parse::<DecimalNumber>().unwrap().as_float());
+        let error = "1.2.3".parse::<DecimalNumber>().unwrap_err();
+        assert_eq!(Some(Span::new(3, 4)), error.span);
+        assert!("-1".parse::<DecimalNumber>().is_err());
+        assert_eq!(100000.0, "1e5".parse::<DecimalNumber>().unwrap().as_float());
+        assert_eq!(0.0015, "1.5E-3".parse::<DecimalNumber>().unwrap().as_float());
+        assert_eq!(23.0, "23.".parse::<DecimalNumber>().unwrap().as_float());
+        assert_eq!("23.", DecimalNumber::new("23.").to_string());
+        assert_eq!("7.8800e4", DecimalNumber::new("7.8800E+4").to_string());
+        assert!("".parse::<DecimalNumber>().is_err());
+        assert!(".".parse::<DecimalNumber>().is_err());
+        assert!("e5".parse::<DecimalNumber>().is_err());
+        assert_eq!(Some(Span::new(1, 2)), "1e".parse::<DecimalNumber>().unwrap_err().span);
+        assert!("1e+".parse::<DecimalNumber>().is_err());
+        assert!("1e2.5".parse::<DecimalNumber>().is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_new() {
+        DecimalNumber::new("12a");
+    }
 }
@@ -0,0 +1,661 @@
+use crate::arithmetic::Rounding;
+use crate::error::Error;
+use crate::format::{self, Precision};
+use crate::measurement::WeightedMean;
+use crate::parser::{expand, expand_weighted_mean, expand_with, parse, Backend};
+use crate::propagation::{Inputs, Order, Quantity};
+use crate::value::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+///A set of named values, shared by the evaluation of several expressions.
+///
+///The measurements declared with `define` are the inputs from which uncertainties
+///are propagated. They may be correlated with each other, and every result keeps
+///track of which inputs it depends on: `x - x` is exactly zero, and the covariance
+///between two results can be obtained with `covariance`.
+///
+///Example:
+///```
+///use scicalc_rs::environment::Environment;
+///use scicalc_rs::measurement::Measurement;
+///
+///let mut env = Environment::new();
+///env.define("slope", Measurement::new(2.0, 0.1));
+///env.define("intercept", Measurement::new(1.0, 0.2));
+///env.set_correlation("slope", "intercept", -0.9);
+///
+///let y = env.eval("slope * 3 + intercept");
+///let cov = env.covariance("slope * 3 + intercept", "slope * 4 + intercept");
+///```
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    variables: HashMap<String, Quantity>,
+    measurements: HashMap<String, usize>, //Input index of each declared measurement
+    labeled: HashSet<String>, //Declared measurements with several labeled components, one input each
+    inputs: Inputs,
+    order: Order,
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        Environment::default()
+    }
+
+    ///Sets the order of the Taylor expansion used to propagate uncertainties
+    pub fn set_order(&mut self, order: Order) {
+        self.order = order;
+    }
+
+    ///Declares a named value. Measurements become independent
+    ///inputs, until a correlation is set between them.
+    ///
+    ///Each labeled component of a measurement is a separate input, so the correlations
+    ///of a measurement with several components can't be set.
+    pub fn define<V: Into<Value>>(&mut self, name: &str, value: V) {
+        self.labeled.remove(name);
+        match value.into() {
+            Value::Measurement(m) => {
+                self.measurements.insert(name.into(), self.inputs.len());
+                let input = self.inputs.push_measurement(&m);
+                self.variables.insert(name.into(), input);
+            }
+            Value::Labeled(m) => {
+                self.measurements.insert(name.into(), self.inputs.len());
+                if m.components().len() > 1 {
+                    self.labeled.insert(name.into());
+                }
+                let input = self.inputs.push_labeled(&m);
+                self.variables.insert(name.into(), input);
+            }
+            v => {
+                self.measurements.remove(name);
+                self.variables.insert(name.into(), Quantity::Exact(v));
+            }
+        }
+    }
+
+    ///Evaluates the expression and stores its result under `name`.
+    ///
+    ///The result remains correlated with the measurements it was computed from.
+    pub fn assign(&mut self, name: &str, input: &str) -> Value {
+        match self.try_assign(name, input) {
+            Ok(value) => value,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    ///Same as `assign`, but returns the first error found in the expression
+    pub fn try_assign(&mut self, name: &str, input: &str) -> Result<Value, Error> {
+        Ok(self.try_assign_with(name, input, &())?.0)
+    }
+
+    ///Same as `try_assign`, also computing the result in the arithmetic of `backend`
+    pub(crate) fn try_assign_with<B: Backend>(&mut self, name: &str, input: &str, backend: &B) -> Result<(Value, B::Value), Error> {
+        let (result, value) = expand_with(&parse(input)?, &self.variables, &mut self.inputs, backend)?;
+        self.measurements.remove(name);
+        self.labeled.remove(name);
+        self.variables.insert(name.into(), result.clone());
+        Ok((self.inputs.value(result, self.order), value))
+    }
+
+    ///Returns the value stored under `name`, if any
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.variables
+            .get(name)
+            .map(|q| self.inputs.value(q.clone(), self.order))
+    }
+
+    ///Evaluates the expression, panicking if it is not valid. See `try_eval`.
+    pub fn eval(&self, input: &str) -> Value {
+        match self.try_eval(input) {
+            Ok(value) => value,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    ///Evaluates the expression, or returns the first error found in it
+    pub fn try_eval(&self, input: &str) -> Result<Value, Error> {
+        Ok(self.try_eval_with(input, &())?.0)
+    }
+
+    ///Same as `try_eval`, also computing the result in the arithmetic of `backend`
+    pub(crate) fn try_eval_with<B: Backend>(&self, input: &str, backend: &B) -> Result<(Value, B::Value), Error> {
+        let mut inputs = self.inputs.clone();
+        let (result, value) = expand_with(&parse(input)?, &self.variables, &mut inputs, backend)?;
+        Ok((inputs.value(result, self.order), value))
+    }
+
+    ///Evaluates a weighted mean such as `wmean(a, b, c)`, along with the chi-square of its
+    ///arguments around it and the Birge ratio, see `WeightedMean`.
+    ///Returns none if the expression is not a call to `wmean`.
+    pub fn try_weighted_mean(&self, input: &str) -> Result<Option<WeightedMean>, Error> {
+        let mut inputs = self.inputs.clone();
+        match expand_weighted_mean(&parse(input)?, &self.variables, &mut inputs)? {
+            Some((mean, chi_squared, ndf)) => {
+                let mean = match inputs.value(mean, self.order) {
+                    Value::Measurement(m) => m,
+                    Value::Labeled(m) => m.combined(),
+                    v => unreachable!("the weighted mean has no uncertainty: {}", v),
+                };
+                Ok(Some(WeightedMean { mean, chi_squared, ndf }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    ///Evaluates the expression with the given inputs
+    fn expand(&self, input: &str, inputs: &mut Inputs) -> Result<Quantity, Error> {
+        expand(&parse(input)?, &self.variables, inputs)
+    }
+
+    ///Sets the correlation coefficient ρ between two declared measurements, panicking if
+    ///it is not valid. See `try_set_correlation`.
+    pub fn set_correlation(&mut self, a: &str, b: &str, rho: f64) {
+        if let Err(e) = self.try_set_correlation(a, b, rho) {
+            panic!("{}", e);
+        }
+    }
+
+    ///Sets the correlation coefficient ρ between two declared measurements, or fails if
+    ///either of them is not declared, if ρ is not between -1 and 1, or if it is inconsistent
+    ///with the correlations already set.
+    ///
+    ///The correlations must form a positive semi-definite matrix, e.g. a and c can't be
+    ///anticorrelated if both are strongly correlated with b. Since the correlations that were
+    ///not set yet are zero, set those of a strongly correlated group at once with
+    ///`try_set_covariance_matrix`.
+    pub fn try_set_correlation(&mut self, a: &str, b: &str, rho: f64) -> Result<(), Error> {
+        let (i, j) = (self.input_index(a)?, self.input_index(b)?);
+        let mut inputs = self.inputs.clone();
+        correlate(&mut inputs, (a, i), (b, j), rho)?;
+        self.replace_inputs(inputs)
+    }
+
+    ///Sets the covariance between two declared measurements, panicking if it is not valid.
+    ///See `try_set_covariance`.
+    pub fn set_covariance(&mut self, a: &str, b: &str, covariance: f64) {
+        if let Err(e) = self.try_set_covariance(a, b, covariance) {
+            panic!("{}", e);
+        }
+    }
+
+    ///Sets the covariance between two declared measurements. The covariance
+    ///of a measurement with itself is its variance, i.e. sigma squared.
+    ///
+    ///Fails as `try_set_correlation` does, or if a variance is negative.
+    pub fn try_set_covariance(&mut self, a: &str, b: &str, covariance: f64) -> Result<(), Error> {
+        let mut inputs = self.inputs.clone();
+        self.set_covariance_of(&mut inputs, a, b, covariance)?;
+        self.replace_inputs(inputs)
+    }
+
+    ///Sets the full covariance matrix of the given declared measurements, panicking if it
+    ///is not valid. See `try_set_covariance_matrix`.
+    pub fn set_covariance_matrix(&mut self, names: &[&str], matrix: &[Vec<f64>]) {
+        if let Err(e) = self.try_set_covariance_matrix(names, matrix) {
+            panic!("{}", e);
+        }
+    }
+
+    ///Sets the full covariance matrix of the given declared measurements, where
+    ///`matrix[i][j]` is the covariance between `names[i]` and `names[j]`.
+    ///
+    ///The diagonal replaces the uncertainties the measurements were declared with.
+    ///Fails without changing any of them if the matrix is not square, symmetric and
+    ///positive semi-definite.
+    pub fn try_set_covariance_matrix(&mut self, names: &[&str], matrix: &[Vec<f64>]) -> Result<(), Error> {
+        if matrix.len() != names.len() || matrix.iter().any(|row| row.len() != names.len()) {
+            return Err(Error::unlocated(format!("covariance matrix must be {0}x{0}", names.len())));
+        }
+        let mut inputs = self.inputs.clone();
+        for (i, name) in names.iter().enumerate() {
+            self.set_covariance_of(&mut inputs, name, name, matrix[i][i])?;
+        }
+        for i in 0..names.len() {
+            for j in (i + 1)..names.len() {
+                if (matrix[i][j] - matrix[j][i]).abs() > 1e-12 * matrix[i][j].abs().max(matrix[j][i].abs()) {
+                    return Err(Error::unlocated(format!("covariance matrix is not symmetric at ({}, {})", i, j)));
+                }
+                self.set_covariance_of(&mut inputs, names[i], names[j], matrix[i][j])?;
+            }
+        }
+        self.replace_inputs(inputs)
+    }
+
+    ///Sets a covariance in the given inputs, see `try_set_covariance`
+    fn set_covariance_of(&self, inputs: &mut Inputs, a: &str, b: &str, covariance: f64) -> Result<(), Error> {
+        let (i, j) = (self.input_index(a)?, self.input_index(b)?);
+        if i == j {
+            if covariance.is_nan() || covariance < 0.0 {
+                return Err(Error::unlocated(format!("variance of \'{}\' cannot be negative, got {}", a, covariance)));
+            }
+            inputs.set_sigma(i, covariance.sqrt());
+            return Ok(());
+        }
+        let sigmas = inputs.sigma(i) * inputs.sigma(j);
+        let rho = if covariance == 0.0 { 0.0 } else { covariance / sigmas };
+        correlate(inputs, (a, i), (b, j), rho)
+    }
+
+    ///Replaces the inputs, if their correlations are consistent
+    fn replace_inputs(&mut self, inputs: Inputs) -> Result<(), Error> {
+        if !inputs.is_positive_semidefinite() {
+            return Err(Error::unlocated("the correlations are inconsistent: their matrix is not positive semi-definite"));
+        }
+        self.inputs = inputs;
+        Ok(())
+    }
+
+    ///Covariance between the results of two expressions, panicking if either is not valid.
+    ///See `try_covariance`.
+    pub fn covariance(&self, a: &str, b: &str) -> f64 {
+        match self.try_covariance(a, b) {
+            Ok(covariance) => covariance,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    ///Covariance between the results of two expressions, or the first error found in them
+    pub fn try_covariance(&self, a: &str, b: &str) -> Result<f64, Error> {
+        let mut inputs = self.inputs.clone();
+        let x = self.expand(a, &mut inputs)?;
+        let y = self.expand(b, &mut inputs)?;
+        Ok(inputs.covariance(&x, &y, self.order))
+    }
+
+    ///Correlation coefficient between the results of two expressions, panicking if either
+    ///is not valid. See `try_correlation`.
+    pub fn correlation(&self, a: &str, b: &str) -> f64 {
+        match self.try_correlation(a, b) {
+            Ok(correlation) => correlation,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    ///Correlation coefficient between the results of two expressions, or the first error found in them
+    pub fn try_correlation(&self, a: &str, b: &str) -> Result<f64, Error> {
+        let mut inputs = self.inputs.clone();
+        let x = self.expand(a, &mut inputs)?;
+        let y = self.expand(b, &mut inputs)?;
+        let covariance = inputs.covariance(&x, &y, self.order);
+        let variances = inputs.covariance(&x, &x, self.order) * inputs.covariance(&y, &y, self.order);
+        Ok(covariance / variances.sqrt())
+    }
+
+    ///Evaluates several expressions together, returning their means and
+    ///uncertainties along with the covariance matrix between them
+    pub fn eval_all(&self, inputs: &[&str]) -> JointResult {
+        match self.try_eval_all(inputs) {
+            Ok(result) => result,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    ///Same as `eval_all`, but returns the first error found in the expressions
+    pub fn try_eval_all(&self, inputs: &[&str]) -> Result<JointResult, Error> {
+        let mut measurements = self.inputs.clone();
+        let mut results: Vec<Quantity> = Vec::new();
+        for input in inputs {
+            match expand(&parse(input)?, &self.variables, &mut measurements)? {
+                Quantity::Exact(Value::Comparison(_)) => {
+                    return Err(Error::unlocated(format!("a comparison has no mean value: {}", input)))
+                }
+                q => results.push(q),
+            }
+        }
+        let covariance: Vec<Vec<f64>> = results
+            .iter()
+            .map(|x| {
+                results
+                    .iter()
+                    .map(|y| measurements.covariance(x, y, self.order))
+                    .collect()
+            })
+            .collect();
+        Ok(JointResult {
+            names: inputs.iter().map(|input| input.to_string()).collect(),
+            means: results.iter().map(|x| measurements.mean(x, self.order)).collect(),
+            sigmas: (0..results.len()).map(|i| covariance[i][i].sqrt()).collect(),
+            covariance,
+        })
+    }
+
+    fn input_index(&self, name: &str) -> Result<usize, Error> {
+        if self.labeled.contains(name) {
+            return Err(Error::unlocated(format!("\'{}\' has several labeled components, so its covariances can't be set", name)));
+        }
+        match self.measurements.get(name) {
+            Some(&i) => Ok(i),
+            None => Err(Error::unlocated(format!("\'{}\' is not a declared measurement", name))),
+        }
+    }
+}
+
+///Sets the correlation coefficient between two inputs of the given measurements
+fn correlate(inputs: &mut Inputs, (a, i): (&str, usize), (b, j): (&str, usize), rho: f64) -> Result<(), Error> {
+    if i == j {
+        return Err(Error::unlocated(format!("cannot set the correlation of \'{}\' with itself", a)));
+    }
+    if !(-1.0..=1.0).contains(&rho) {
+        return Err(Error::unlocated(format!("correlation coefficient of \'{}\' and \'{}\' must be between -1 and 1, got {}", a, b, rho)));
+    }
+    inputs.set_correlation(i, j, rho);
+    Ok(())
+}
+
+///Means, uncertainties and covariance matrix of several
+///results evaluated over the same environment
+#[derive(Debug, Clone, PartialEq)]
+pub struct JointResult {
+    pub names: Vec<String>,
+    pub means: Vec<f64>,
+    pub sigmas: Vec<f64>,
+    pub covariance: Vec<Vec<f64>>,
+}
+
+impl JointResult {
+    ///Correlation matrix of the results.
+    ///
+    ///Results without uncertainty are taken as uncorrelated with everything else.
+    pub fn correlation(&self) -> Vec<Vec<f64>> {
+        let n = self.means.len();
+        (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| {
+                        let sigmas = self.sigmas[i] * self.sigmas[j];
+                        if i == j {
+                            1.0
+                        } else if sigmas == 0.0 {
+                            0.0
+                        } else {
+                            self.covariance[i][j] / sigmas
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+///Prints the results as a table, followed by their covariance matrix
+impl fmt::Display for JointResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.table(Precision::Full, Rounding::default()))
+    }
+}
+
+impl JointResult {
+    ///The results as a table, with their means and uncertainties rounded to the given
+    ///precision in the given way, followed by their covariance matrix
+    pub fn table(&self, precision: Precision, rounding: Rounding) -> String {
+        let n = self.means.len();
+        let correlation = self.correlation();
+
+        let mut rows = vec![vec!["#".to_string(), "expression".into(), "mean".into(), "sigma".into()]];
+        rows[0].extend((1..=n).map(|j| format!("[{}]", j)));
+        for (i, rhos) in correlation.iter().enumerate() {
+            let (mean, sigma) = format::measurement(self.means[i], self.sigmas[i], precision, rounding);
+            let mut row = vec![format!("[{}]", i + 1), self.names[i].clone(), mean, sigma];
+            row.extend(rhos.iter().map(|rho| format!("{:.3}", rho)));
+            rows.push(row);
+        }
+        let mut table = write_table(&rows);
+
+        table += "\ncovariance:\n";
+        let mut rows = vec![vec![String::new()]];
+        rows[0].extend((1..=n).map(|j| format!("[{}]", j)));
+        for (i, covariances) in self.covariance.iter().enumerate() {
+            let mut row = vec![format!("[{}]", i + 1)];
+            row.extend(covariances.iter().map(|c| c.to_string()));
+            rows.push(row);
+        }
+        table + &write_table(&rows)
+    }
+}
+
+///Writes the rows with their columns aligned to the left
+fn write_table(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|j| rows.iter().filter_map(|row| row.get(j)).map(|cell| cell.chars().count()).max().unwrap_or(0))
+        .collect();
+    let mut table = String::new();
+    for row in rows {
+        let line: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(j, cell)| format!("{:width$}", cell, width = widths[j]))
+            .collect();
+        table += line.join("  ").trim_end();
+        table.push('\n');
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::{LabeledMeasurement, Measurement};
+    use float_cmp::{ApproxEq, F64Margin};
+
+    fn measurement(value: Value) -> Measurement {
+        match value {
+            Value::Measurement(m) => m,
+            v => panic!("Error: {} is not a measurement", v),
+        }
+    }
+
+    fn fit() -> Environment {
+        let mut env = Environment::new();
+        env.define("a", Measurement::new(2.0, 0.1));
+        env.define("b", Measurement::new(1.0, 0.2));
+        env.set_correlation("a", "b", -0.9);
+        env
+    }
+
+    #[test]
+    fn test_correlated_fit_parameters() {
+        //y = a*x + b, so Var[y] = x²σa² + σb² + 2xρσaσb
+        let env = fit();
+        let x = 3.0;
+        let variance: f64 = x * x * 0.01 + 0.04 + 2.0 * x * -0.9 * 0.1 * 0.2;
+        let y = measurement(env.eval("a * 3 + b"));
+        assert!(y.approx_eq(Measurement::new(7.0, variance.sqrt()), F64Margin::default()));
+    }
+
+    #[test]
+    fn test_covariance_matrix() {
+        let mut env = Environment::new();
+        env.define("a", Measurement::new(2.0, 1.0));
+        env.define("b", Measurement::new(1.0, 1.0));
+        env.set_covariance_matrix(&["a", "b"], &[vec![0.01, -0.018], vec![-0.018, 0.04]]);
+
+        let expected = fit();
+        for input in &["a * 3 + b", "a / b", "a ^ 2 - b"] {
+            assert!(measurement(env.eval(input)).approx_eq(measurement(expected.eval(input)), F64Margin::default()));
+        }
+    }
+
+    #[test]
+    fn test_repeated_variables() {
+        let mut env = Environment::new();
+        env.define("x", Measurement::new(3.0, 0.1));
+        assert_eq!(Measurement::new(0.0, 0.0), measurement(env.eval("x - x")));
+        assert!(measurement(env.eval("x * x")).approx_eq(measurement(env.eval("x ^ 2")), F64Margin::default()));
+        assert!(measurement(env.eval("x + x")).approx_eq(Measurement::new(6.0, 0.2), F64Margin::default()));
+    }
+
+    #[test]
+    fn test_covariance_between_results() {
+        let mut env = fit();
+        //Independent inputs: Cov(c + d, c - d) = σc² - σd²
+        env.define("c", Measurement::new(5.0, 0.3));
+        env.define("d", Measurement::new(4.0, 0.4));
+        assert!(env.covariance("c + d", "c - d").approx_eq(0.09 - 0.16, F64Margin::default()));
+        //A result is fully correlated with itself
+        assert!(env.correlation("a * 3 + b", "a * 3 + b").approx_eq(1.0, F64Margin::default()));
+        //Cov(a + b, a) = σa² + ρσaσb
+        assert!(env.covariance("a + b", "a").approx_eq(0.01 - 0.9 * 0.02, F64Margin::default()));
+        //Exact numbers and unrelated measurements have no covariance
+        assert_eq!(0.0, env.covariance("a", "2 * π"));
+        assert_eq!(0.0, env.covariance("a", "c"));
+    }
+
+    #[test]
+    fn test_assigned_results_stay_correlated() {
+        let mut env = fit();
+        env.assign("y1", "a * 1 + b");
+        env.assign("y2", "a * 2 + b");
+        assert_eq!(env.covariance("y1", "y2"), env.covariance("a * 1 + b", "a * 2 + b"));
+        //Literal measurements in an assignment are stored as inputs too
+        env.assign("z", "a + (1 ± 0.5)");
+        assert!(measurement(env.eval("z - a")).approx_eq(Measurement::new(1.0, 0.5), F64Margin::default()));
+    }
+
+    #[test]
+    fn test_second_order_with_correlation() {
+        //E[ab] = μaμb + ρσaσb
+        let mut env = fit();
+        env.set_order(Order::Second);
+        let ab = measurement(env.eval("a * b"));
+        assert!(ab.mean.approx_eq(2.0 - 0.9 * 0.1 * 0.2, F64Margin::default()));
+    }
+
+    #[test]
+    fn test_exact_variables() {
+        let mut env = Environment::new();
+        env.define("g", 9.81);
+        assert_eq!(Value::Number(9.81 * 2.0), env.eval("g * 2"));
+        assert_eq!(None, env.get("h"));
+    }
+
+    #[test]
+    fn test_eval_all() {
+        let mut env = fit();
+        env.define("g", 9.81);
+        let joint = env.eval_all(&["a * 1 + b", "a * 2 + b", "g"]);
+        assert_eq!(vec![3.0, 5.0, 9.81], joint.means);
+        for (i, y) in ["a * 1 + b", "a * 2 + b"].iter().enumerate() {
+            assert!(joint.sigmas[i].approx_eq(measurement(env.eval(y)).sigma, F64Margin::default()));
+            for (j, z) in ["a * 1 + b", "a * 2 + b"].iter().enumerate() {
+                assert!(joint.covariance[i][j].approx_eq(env.covariance(y, z), F64Margin::default()));
+            }
+        }
+        let correlation = joint.correlation();
+        assert!(correlation[0][1].approx_eq(env.correlation("a * 1 + b", "a * 2 + b"), F64Margin::default()));
+        assert_eq!(correlation[0][1], correlation[1][0]);
+        //The exact number is uncorrelated with the other results
+        assert_eq!(vec![0.0, 0.0, 1.0], correlation[2]);
+        assert_eq!(0.0, joint.sigmas[2]);
+    }
+
+    #[test]
+    fn test_joint_table() {
+        let mut env = Environment::new();
+        env.define("x", Measurement::new(1.0, 0.5));
+        let table = env.eval_all(&["x", "-x"]).to_string();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!("#    expression  mean  sigma  [1]     [2]", lines[0]);
+        assert_eq!("[1]  x           1     0.5    1.000   -1.000", lines[1]);
+        assert_eq!("[2]  -x          -1    0.5    -1.000  1.000", lines[2]);
+        assert_eq!("covariance:", lines[4]);
+        assert_eq!("[1]  0.25   -0.25", lines[6]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_correlation() {
+        let mut env = fit();
+        env.set_correlation("a", "b", 1.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "not positive semi-definite")]
+    fn test_inconsistent_correlations() {
+        //b and c can't be strongly correlated if a and b are strongly anticorrelated, while a and c are not
+        let mut env = fit();
+        env.define("c", Measurement::new(3.0, 0.1));
+        env.set_correlation("b", "c", 0.9);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_correlation_of_exact_number() {
+        let mut env = fit();
+        env.define("g", 9.81);
+        env.set_correlation("a", "g", 0.5);
+    }
+
+    #[test]
+    fn test_invalid_covariances() {
+        let mut env = fit();
+        env.define("c", Measurement::new(3.0, 0.1));
+        let error = env.try_set_correlation("a", "x", 0.5).unwrap_err();
+        assert_eq!("\'x\' is not a declared measurement", error.message);
+        assert!(env.try_set_covariance("a", "a", -1.0).is_err());
+        assert!(env.try_covariance("a", "a +").is_err());
+        assert!(env.try_correlation("a", "x").is_err());
+        assert!(env.try_set_covariance_matrix(&["a", "b"], &[vec![0.01, 0.0], vec![0.01, 0.04]]).is_err());
+        //b and c can't be strongly correlated if a and b are strongly anticorrelated, while a and c are not
+        let error = env.try_set_correlation("b", "c", 0.9).unwrap_err();
+        assert_eq!("the correlations are inconsistent: their matrix is not positive semi-definite", error.message);
+        assert_eq!(0.0, env.covariance("b", "c"));
+        //Set at once, they are consistent
+        let (a, b, c) = (0.1 * 0.1, 0.2 * 0.2, 0.1 * 0.1);
+        let (ab, bc, ac) = (-0.9 * 0.1 * 0.2, 0.9 * 0.2 * 0.1, -0.81 * 0.1 * 0.1);
+        env.set_covariance_matrix(&["a", "b", "c"], &[vec![a, ab, ac], vec![ab, b, bc], vec![ac, bc, c]]);
+        assert!(env.correlation("a", "c").approx_eq(-0.81, F64Margin::default()));
+        //A matrix which is not positive semi-definite changes nothing
+        let matrix = [vec![a, ab, -ac], vec![ab, b, bc], vec![-ac, bc, c]];
+        assert!(env.try_set_covariance_matrix(&["a", "b", "c"], &matrix).is_err());
+        assert!(env.correlation("a", "c").approx_eq(-0.81, F64Margin::default()));
+    }
+
+    #[test]
+    fn test_defined_components() {
+        let mut env = Environment::new();
+        env.define("x", LabeledMeasurement::with_components(2.0, &[("stat", 0.3), ("syst", 0.4)]));
+        env.define("y", LabeledMeasurement::with_components(1.0, &[("stat", 0.4)]));
+        let expected = LabeledMeasurement::with_components(3.0, &[("stat", 0.5), ("syst", 0.4)]);
+        match env.eval("x + y") {
+            Value::Labeled(sum) => assert!(sum.approx_eq(expected, F64Margin::default())),
+            v => panic!("{} has no components", v),
+        }
+        match env.eval("x - x") {
+            Value::Labeled(zero) => {
+                assert_eq!((0.0, 0.0), (zero.mean(), zero.sigma()));
+                assert_eq!(Some(0.0), zero.component("syst"));
+            }
+            v => panic!("{} has no components", v),
+        }
+        //A single component is the only input of its measurement
+        env.define("z", Measurement::new(5.0, 0.1));
+        env.set_correlation("y", "z", 0.5);
+        assert!(env.covariance("y", "z").approx_eq(0.5 * 0.4 * 0.1, F64Margin::default()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_correlation_of_components() {
+        let mut env = fit();
+        env.define("x", LabeledMeasurement::with_components(2.0, &[("stat", 0.3), ("syst", 0.4)]));
+        env.set_correlation("a", "x", 0.5);
+    }
+
+    #[test]
+    fn test_weighted_mean() {
+        let margin = F64Margin::default();
+        let mut env = Environment::new();
+        env.define("a", Measurement::new(1.0, 0.1));
+        let result = env.try_weighted_mean("wmean(a, 2 ± 0.2)").unwrap().unwrap();
+        let expected = Measurement::weighted_mean(&[Measurement::new(1.0, 0.1), Measurement::new(2.0, 0.2)]).unwrap();
+        assert!(result.mean.approx_eq(expected.mean, margin));
+        assert!(result.chi_squared.approx_eq(expected.chi_squared, margin));
+        assert_eq!(1, result.ndf);
+        assert_eq!(None, env.try_weighted_mean("a * 2").unwrap());
+        assert!(env.try_weighted_mean("wmean(a)").unwrap().unwrap().p_value().is_nan());
+        assert!(env.try_weighted_mean("wmean(a, 2)").is_err());
+    }
+}
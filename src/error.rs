@@ -0,0 +1,58 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+///Location of (part of) an expression, in characters from its start.
+///The end is exclusive, e.g. the span of `x` in `2 * x` is 4..5
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    ///The smallest span covering both spans
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+///Error found while reading or evaluating an expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub message: String,
+    pub span: Option<Span>, //Part of the expression where the error was found, if known
+}
+
+impl Error {
+    pub fn new<S: Into<String>>(message: S, span: Span) -> Error {
+        Error { message: message.into(), span: Some(span) }
+    }
+
+    ///An error that can't be pinned to a part of the expression
+    pub fn unlocated<S: Into<String>>(message: S) -> Error {
+        Error { message: message.into(), span: None }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{} (at {})", self.message, span),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
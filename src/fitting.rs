@@ -0,0 +1,223 @@
+//!Fitting of straight lines to measured points
+
+use crate::environment::Environment;
+use crate::error::Error;
+use crate::measurement::Measurement;
+use crate::statistics;
+use std::fmt;
+
+///Result of fitting a straight line y = slope·x + intercept
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearFit {
+    pub slope: Measurement,
+    pub intercept: Measurement,
+    pub covariance: f64,  //covariance between the slope and the intercept
+    pub chi_squared: f64, //weighted sum of the squared residuals
+    pub ndf: usize,       //number of degrees of freedom of the chi-square, n - 2
+}
+
+impl LinearFit {
+    ///Correlation coefficient between the slope and the intercept
+    pub fn correlation(&self) -> f64 {
+        self.covariance / (self.slope.sigma * self.intercept.sigma)
+    }
+
+    ///Reduced chi-square χ²/ndf, which should be close to one for a good fit.
+    ///
+    ///Undefined (NaN) when there are only two points.
+    pub fn reduced_chi_squared(&self) -> f64 {
+        self.chi_squared / self.ndf as f64
+    }
+
+    ///Probability of a chi-square at least as large, if the line is the right model
+    pub fn p_value(&self) -> f64 {
+        statistics::chi_squared_p_value(self.chi_squared, self.ndf)
+    }
+
+    ///Declares the slope and the intercept in an environment, keeping their covariance
+    ///so that expressions involving both of them have the right uncertainty.
+    ///
+    ///Fails if both are given the same name, or as `Environment::try_set_covariance` does.
+    pub fn define(&self, environment: &mut Environment, slope: &str, intercept: &str) -> Result<(), Error> {
+        if slope == intercept {
+            return Err(Error::unlocated(format!("the slope and the intercept can't both be named \'{}\'", slope)));
+        }
+        environment.define(slope, self.slope);
+        environment.define(intercept, self.intercept);
+        environment.try_set_covariance(slope, intercept, self.covariance)
+    }
+}
+
+impl fmt::Display for LinearFit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "slope = {}", self.slope)?;
+        writeln!(f, "intercept = {}", self.intercept)?;
+        writeln!(f, "correlation = {}", self.correlation())?;
+        write!(f, "χ²/ndf = {}/{}", self.chi_squared, self.ndf)
+    }
+}
+
+///Fits a straight line y = slope·x + intercept to the points (x, y), weighting each
+///one by its uncertainties.
+///
+///Without uncertainties in `x`, this is the weighted least-squares fit. Otherwise the
+///uncertainties in both variables are taken into account with York's method
+///(York et al., "Unified equations for the slope, intercept, and standard errors of
+///the best straight line", 2004), assuming the errors in x and y of each point are independent.
+///
+///Fails if `x` and `y` have different lengths, if there are fewer than two points, if a
+///value of y has no uncertainty, or if all the points have the same x.
+pub fn linear_fit(x: &[Measurement], y: &[Measurement]) -> Result<LinearFit, Error> {
+    if x.len() != y.len() {
+        return Err(Error::unlocated(format!("there are {} values of x, but {} values of y", x.len(), y.len())));
+    }
+    if x.len() < 2 {
+        return Err(Error::unlocated(format!("fitting a line needs at least two points, got {}", x.len())));
+    }
+    if let Some(p) = y.iter().find(|p| p.sigma.is_nan() || p.sigma <= 0.0) {
+        return Err(Error::unlocated(format!("every value of y must have an uncertainty, got {}", p)));
+    }
+    let weights = |slope: f64| -> Vec<f64> {
+        x.iter()
+            .zip(y)
+            .map(|(x, y)| 1.0 / (y.sigma * y.sigma + slope * slope * x.sigma * x.sigma))
+            .collect()
+    };
+    let weighted_mean = |w: &[f64], values: &[f64]| -> f64 {
+        w.iter().zip(values).map(|(w, v)| w * v).sum::<f64>() / w.iter().sum::<f64>()
+    };
+    let xs: Vec<f64> = x.iter().map(|p| p.mean).collect();
+    let ys: Vec<f64> = y.iter().map(|p| p.mean).collect();
+
+    //Starting from the slope of the unweighted fit, iterate until the slope is stable
+    let mut slope = {
+        let (x_mean, y_mean) = (statistics::mean(&xs), statistics::mean(&ys));
+        let sxy: f64 = xs.iter().zip(&ys).map(|(x, y)| (x - x_mean) * (y - y_mean)).sum();
+        let sxx: f64 = xs.iter().map(|x| (x - x_mean) * (x - x_mean)).sum();
+        sxy / sxx
+    };
+    let mut w = weights(slope);
+    let mut beta = vec![0.0; xs.len()];
+    for _ in 0..1000 {
+        let (x_bar, y_bar) = (weighted_mean(&w, &xs), weighted_mean(&w, &ys));
+        let mut numerator = 0.0;
+        let mut denominator = 0.0;
+        for i in 0..xs.len() {
+            let (u, v) = (xs[i] - x_bar, ys[i] - y_bar);
+            beta[i] = w[i] * (u * y[i].sigma * y[i].sigma + slope * v * x[i].sigma * x[i].sigma);
+            numerator += w[i] * beta[i] * v;
+            denominator += w[i] * beta[i] * u;
+        }
+        let previous = slope;
+        slope = numerator / denominator;
+        w = weights(slope);
+        if (slope - previous).abs() <= 1e-15 * slope.abs() {
+            break;
+        }
+    }
+    if !slope.is_finite() {
+        return Err(Error::unlocated("cannot fit a line to points with a single value of x"));
+    }
+
+    let (x_bar, y_bar) = (weighted_mean(&w, &xs), weighted_mean(&w, &ys));
+    let intercept = y_bar - slope * x_bar;
+    //Adjusted values of x, from which the uncertainties are computed
+    let adjusted: Vec<f64> = (0..xs.len()).map(|i| x_bar + beta[i]).collect();
+    let adjusted_mean = weighted_mean(&w, &adjusted);
+    let slope_variance = 1.0 / w.iter().zip(&adjusted).map(|(w, x)| w * (x - adjusted_mean).powi(2)).sum::<f64>();
+    let intercept_variance = 1.0 / w.iter().sum::<f64>() + adjusted_mean * adjusted_mean * slope_variance;
+    let chi_squared = (0..xs.len()).map(|i| w[i] * (ys[i] - slope * xs[i] - intercept).powi(2)).sum();
+
+    Ok(LinearFit {
+        slope: Measurement::new(slope, slope_variance.sqrt()),
+        intercept: Measurement::new(intercept, intercept_variance.sqrt()),
+        covariance: -adjusted_mean * slope_variance,
+        chi_squared,
+        ndf: xs.len() - 2,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use float_cmp::{ApproxEq, F64Margin};
+
+    fn exact(values: &[f64]) -> Vec<Measurement> {
+        values.iter().map(|&x| Measurement::new(x, 0.0)).collect()
+    }
+
+    #[test]
+    fn test_weighted_least_squares() {
+        let margin = F64Margin { epsilon: 1e-12, ulps: 4 };
+        let x = exact(&[0.0, 1.0, 2.0, 3.0]);
+        let y = vec![
+            Measurement::new(1.1, 0.1),
+            Measurement::new(2.9, 0.1),
+            Measurement::new(5.2, 0.2),
+            Measurement::new(6.9, 0.2),
+        ];
+        let fit = linear_fit(&x, &y).unwrap();
+
+        //Closed form of the weighted least-squares fit
+        let w: Vec<f64> = y.iter().map(|p| 1.0 / (p.sigma * p.sigma)).collect();
+        let sum = |f: &dyn Fn(usize) -> f64| (0..4).map(|i| w[i] * f(i)).sum::<f64>();
+        let (s, sx, sy) = (sum(&|_| 1.0), sum(&|i| x[i].mean), sum(&|i| y[i].mean));
+        let (sxx, sxy) = (sum(&|i| x[i].mean * x[i].mean), sum(&|i| x[i].mean * y[i].mean));
+        let delta = s * sxx - sx * sx;
+        assert!(fit.slope.approx_eq(Measurement::new((s * sxy - sx * sy) / delta, (s / delta).sqrt()), margin));
+        assert!(fit.intercept.approx_eq(Measurement::new((sxx * sy - sx * sxy) / delta, (sxx / delta).sqrt()), margin));
+        assert!(fit.covariance.approx_eq(-sx / delta, margin));
+        assert_eq!(2, fit.ndf);
+    }
+
+    #[test]
+    fn test_perfect_line() {
+        let x = exact(&[1.0, 2.0, 3.0]);
+        let y: Vec<Measurement> = [3.0, 5.0, 7.0].iter().map(|&y| Measurement::new(y, 0.1)).collect();
+        let fit = linear_fit(&x, &y).unwrap();
+        assert!(fit.slope.mean.approx_eq(2.0, F64Margin::default()));
+        assert!(fit.intercept.mean.approx_eq(1.0, F64Margin::default()));
+        assert!(fit.chi_squared < 1e-20);
+        assert!(fit.correlation() < 0.0);
+    }
+
+    #[test]
+    fn test_errors_in_both_variables() {
+        //Pearson's data with York's weights, a standard benchmark for York's method
+        let xs = [0.0, 0.9, 1.8, 2.6, 3.3, 4.4, 5.2, 6.1, 6.5, 7.4];
+        let ys = [5.9, 5.4, 4.4, 4.6, 3.5, 3.7, 2.8, 2.8, 2.4, 1.5];
+        let wx = [1000.0, 1000.0, 500.0, 800.0, 200.0, 80.0, 60.0, 20.0, 1.8, 1.0];
+        let wy = [1.0, 1.8, 4.0, 8.0, 20.0, 20.0, 70.0, 70.0, 100.0, 500.0];
+        let x: Vec<Measurement> = xs.iter().zip(&wx).map(|(&x, &w)| Measurement::new(x, 1.0 / f64::sqrt(w))).collect();
+        let y: Vec<Measurement> = ys.iter().zip(&wy).map(|(&y, &w)| Measurement::new(y, 1.0 / f64::sqrt(w))).collect();
+        let fit = linear_fit(&x, &y).unwrap();
+        //Reference values from York et al. (2004)
+        assert!((fit.slope.mean - -0.4805334).abs() < 1e-6);
+        assert!((fit.intercept.mean - 5.479910).abs() < 1e-5);
+        assert!((fit.slope.sigma - 0.0580).abs() < 1e-4);
+        assert!((fit.intercept.sigma - 0.2950).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_define() {
+        let x = exact(&[0.0, 1.0, 2.0]);
+        let y: Vec<Measurement> = [1.0, 2.9, 5.1].iter().map(|&y| Measurement::new(y, 0.1)).collect();
+        let fit = linear_fit(&x, &y).unwrap();
+        let mut env = Environment::new();
+        fit.define(&mut env, "a", "b").unwrap();
+        assert!(env.covariance("a", "b").approx_eq(fit.covariance, F64Margin::default()));
+        assert!(env.correlation("a", "b").approx_eq(fit.correlation(), F64Margin { epsilon: 1e-12, ulps: 4 }));
+    }
+
+    #[test]
+    fn test_invalid_points() {
+        let error = linear_fit(&exact(&[0.0, 1.0]), &[Measurement::new(1.0, 0.1)]).unwrap_err();
+        assert_eq!("there are 2 values of x, but 1 values of y", error.message);
+        assert!(linear_fit(&exact(&[0.0]), &[Measurement::new(1.0, 0.1)]).is_err());
+        assert!(linear_fit(&exact(&[0.0, 1.0]), &exact(&[1.0, 2.0])).is_err());
+        let y = [Measurement::new(1.0, 0.1), Measurement::new(2.0, 0.1)];
+        assert!(linear_fit(&exact(&[1.0, 1.0]), &y).is_err());
+        let fit = linear_fit(&exact(&[0.0, 1.0]), &y).unwrap();
+        assert!(fit.define(&mut Environment::new(), "a", "a").is_err());
+    }
+}
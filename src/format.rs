@@ -0,0 +1,168 @@
+//!Rounding of results for display
+
+use crate::arithmetic::{Decimal, Rounding};
+use crate::measurement::{LabeledMeasurement, Measurement};
+use crate::value::Value;
+
+///How many digits of a result are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Precision {
+    ///Every digit needed to recover the exact floating-point number
+    #[default]
+    Full,
+    ///A fixed number of decimal places
+    Decimals(usize),
+    ///The uncertainty with this many significant figures, and the mean rounded to
+    ///the same decimal place. Exact numbers get this many significant figures themselves.
+    SigFigs(usize),
+}
+
+///Formats a number with the given precision, rounding its shortest decimal form in the
+///given way, so that 2.675 is 2.68 with two decimals and half-up rounding
+pub fn number(x: f64, precision: Precision, rounding: Rounding) -> String {
+    match (Decimal::from_f64(x), precision) {
+        (Some(x), Precision::Decimals(_) | Precision::SigFigs(_)) => exact_number(&x, precision, rounding),
+        _ => x.to_string(),
+    }
+}
+
+///Formats the mean and the uncertainty of a measurement with the given precision,
+///returning them separately. The uncertainty decides where both are rounded, see
+///`Measurement::round_to_uncertainty`.
+pub fn measurement(mean: f64, sigma: f64, precision: Precision, rounding: Rounding) -> (String, String) {
+    match precision {
+        Precision::SigFigs(n) if sigma > 0.0 && sigma.is_finite() => Measurement::new(mean, sigma).round_to_uncertainty(n, rounding),
+        Precision::SigFigs(_) => (number(mean, precision, rounding), number(sigma, Precision::Full, rounding)),
+        _ => (number(mean, precision, rounding), number(sigma, precision, rounding)),
+    }
+}
+
+///Formats a value with the given precision, like its `Display` implementation does
+///with all the digits. The components of a measurement are rounded at the same
+///place as its combined uncertainty, unless `combined` is set to show only the latter.
+pub fn value(value: &Value, precision: Precision, rounding: Rounding, combined: bool) -> String {
+    match value {
+        Value::PosNumber(x) | Value::Number(x) => number(*x, precision, rounding),
+        Value::Rational(x) => number(x.to_f64(), precision, rounding),
+        Value::Measurement(m) => measurement_with_components(&LabeledMeasurement::from(*m), precision, rounding, combined),
+        Value::Labeled(m) => measurement_with_components(m, precision, rounding, combined),
+        Value::Comparison(c) => c.to_string(),
+    }
+}
+
+///Same as `value`, but with an exact mean computed with decimal arithmetic (see `arithmetic::mean`),
+///which is rounded in the given way
+pub fn exact_value(value: &Value, mean: &Decimal, precision: Precision, rounding: Rounding, combined: bool) -> String {
+    let m = match value {
+        Value::Measurement(m) => LabeledMeasurement::from(*m),
+        Value::Labeled(m) => m.clone(),
+        Value::Comparison(c) => return c.to_string(),
+        _ => return exact_number(mean, precision, rounding),
+    };
+    let (_, sigma, components) = components(&m, precision, rounding);
+    let mean = match precision {
+        Precision::SigFigs(n) => match sigma_place(m.sigma(), n, rounding) {
+            Some(place) => mean.to_places(place, rounding),
+            None => exact_number(mean, precision, rounding),
+        },
+        _ => exact_number(mean, precision, rounding),
+    };
+    join(mean, sigma, components, combined)
+}
+
+fn exact_number(x: &Decimal, precision: Precision, rounding: Rounding) -> String {
+    match precision {
+        Precision::Full => x.to_string(),
+        Precision::Decimals(n) => x.to_places(n as i32, rounding),
+        Precision::SigFigs(n) => x.to_sig_figs(n, rounding),
+    }
+}
+
+fn measurement_with_components(m: &LabeledMeasurement, precision: Precision, rounding: Rounding, combined: bool) -> String {
+    let (mean, sigma, components) = components(m, precision, rounding);
+    join(mean, sigma, components, combined)
+}
+
+///Writes a measurement from its formatted parts
+fn join(mean: String, sigma: String, components: Vec<(&str, String)>, combined: bool) -> String {
+    if combined || components.is_empty() {
+        return format!("{} ± {}", mean, sigma);
+    }
+    let mut text = mean;
+    for (label, u) in components {
+        if label.is_empty() {
+            text += &format!(" ± {}", u);
+        } else {
+            text += &format!(" ± {} ({})", u, label);
+        }
+    }
+    text
+}
+
+///Formats the mean, the combined uncertainty and the labeled components of a measurement
+///with the given precision. The components are rounded at the same place as the combined uncertainty.
+pub fn components(m: &LabeledMeasurement, precision: Precision, rounding: Rounding) -> (String, String, Vec<(&str, String)>) {
+    let (mean, sigma) = measurement(m.mean(), m.sigma(), precision, rounding);
+    let component = |u: f64| match precision {
+        Precision::SigFigs(n) => match (sigma_place(m.sigma(), n, rounding), Decimal::from_f64(u)) {
+            (Some(place), Some(u)) => u.to_places(place, rounding),
+            _ => number(u, Precision::Full, rounding),
+        },
+        _ => number(u, precision, rounding),
+    };
+    let components = m.components().iter().map(|(label, u)| (label.as_str(), component(*u))).collect();
+    (mean, sigma, components)
+}
+
+///Decimal place of `sig_figs` significant figures of an uncertainty, once rounded (see
+///`Decimal::sig_figs_place`), or none if it is zero or not finite
+fn sigma_place(sigma: f64, sig_figs: usize, rounding: Rounding) -> Option<i32> {
+    Decimal::from_f64(sigma).filter(|sigma| !sigma.is_zero()).map(|sigma| sigma.sig_figs_place(sig_figs, rounding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::eval;
+
+    #[test]
+    fn test_decimal_rounding() {
+        assert_eq!("2.68", number(2.675, Precision::Decimals(2), Rounding::HalfUp));
+        assert_eq!("2.68", number(2.675, Precision::Decimals(2), Rounding::HalfEven));
+        assert_eq!("2.67", number(2.675, Precision::Decimals(2), Rounding::TowardZero));
+        assert_eq!(("1.01".to_string(), "0.01".to_string()), measurement(1.005, 0.01, Precision::SigFigs(1), Rounding::HalfUp));
+        assert_eq!(("1.00".to_string(), "0.01".to_string()), measurement(1.005, 0.01, Precision::SigFigs(1), Rounding::HalfEven));
+        assert_eq!("inf", number(f64::INFINITY, Precision::Decimals(2), Rounding::HalfUp));
+    }
+
+    #[test]
+    fn test_sig_figs() {
+        assert_eq!(("2.70".to_string(), "0.02".to_string()), measurement(2.7, 0.0234, Precision::SigFigs(1), Rounding::HalfUp));
+        assert_eq!(("1235".to_string(), "23".to_string()), measurement(1234.56, 23.4, Precision::SigFigs(2), Rounding::HalfUp));
+        assert_eq!(("12300".to_string(), "200".to_string()), measurement(12345.0, 234.0, Precision::SigFigs(1), Rounding::HalfUp));
+        //0.096 rounds up to 0.1, which has a single decimal
+        assert_eq!(("1.0".to_string(), "0.1".to_string()), measurement(0.987, 0.096, Precision::SigFigs(1), Rounding::HalfUp));
+        assert_eq!("3.14", number(std::f64::consts::PI, Precision::SigFigs(3), Rounding::HalfUp));
+    }
+
+    #[test]
+    fn test_value() {
+        let v = eval("12.34 ± 0.4 (stat) ± 0.2 (syst)");
+        assert_eq!("12.3 ± 0.4 (stat) ± 0.2 (syst)", value(&v, Precision::SigFigs(1), Rounding::HalfUp, false));
+        assert_eq!("12.3 ± 0.4", value(&v, Precision::SigFigs(1), Rounding::HalfUp, true));
+        assert_eq!("12.340 ± 0.400 (stat) ± 0.200 (syst)", value(&v, Precision::Decimals(3), Rounding::HalfUp, false));
+        assert_eq!(eval("1 ± 0.5").to_string(), value(&eval("1 ± 0.5"), Precision::Full, Rounding::HalfUp, false));
+    }
+
+    #[test]
+    fn test_exact_value() {
+        let mean: Decimal = "0.30000000000000000000001".parse().unwrap();
+        let v = eval("(0.1 ± 0.0234 (stat)) + 0.2");
+        assert_eq!("0.30000000000000000000001 ± 0.0234 (stat)", exact_value(&v, &mean, Precision::Full, Rounding::HalfEven, false));
+        assert_eq!("0.30 ± 0.02", exact_value(&v, &mean, Precision::SigFigs(1), Rounding::HalfEven, true));
+        let mean: Decimal = "2.5".parse().unwrap();
+        assert_eq!("2", exact_value(&eval("5 / 2"), &mean, Precision::Decimals(0), Rounding::HalfEven, false));
+        assert_eq!("3", exact_value(&eval("5 / 2"), &mean, Precision::Decimals(0), Rounding::HalfUp, false));
+        assert_eq!("2.500", exact_value(&eval("5 / 2"), &mean, Precision::SigFigs(4), Rounding::HalfEven, false));
+    }
+}
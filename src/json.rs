@@ -0,0 +1,214 @@
+//!Machine-readable output of results, as JSON
+//!
+//!Every evaluated expression becomes an object with a stable schema (version 1):
+//!
+//!```text
+//!{"schema": 1, "expression": "2 ± 0.1", "status": "ok",
+//! "result": {"kind": "measurement", "mean": 2, "sigma": 0.1, "relative": 0.05,
+//!            "dof": null, "components": []},
+//! "warnings": []}
+//!
+//!{"schema": 1, "expression": "2 +", "status": "error",
+//! "error": {"message": "unexpected end of the expression", "span": {"start": 3, "end": 3}},
+//! "warnings": []}
+//!```
+//!
+//!The `kind` of a result is `number`, `measurement` or `comparison`. Numbers have the same
+//!fields as measurements, with a zero `sigma`. Comparisons have `z_score`, `p_value`,
+//!`sigmas` and `agrees` instead. Values that are not finite, such as infinite degrees
+//!of freedom, are `null`. Spans count characters, and their end is exclusive.
+
+use crate::error::Error;
+use crate::measurement::LabeledMeasurement;
+use crate::propagation::Order;
+use crate::value::Value;
+
+///Version of the schema, increased on incompatible changes
+pub const SCHEMA: u32 = 1;
+
+///Relative uncertainty above which first-order propagation is flagged as unreliable
+pub const LARGE_RELATIVE_UNCERTAINTY: f64 = 0.5;
+
+///How the uncertainties of the results were propagated, which their warnings depend on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagation {
+    Taylor(Order), //Taylor expansion of the given order
+    MonteCarlo,
+}
+
+///Warnings about a result which is valid, but may not be trustworthy.
+///
+///Large relative uncertainties are only flagged for a Taylor expansion, which
+///assumes the expression to be nearly linear over the uncertainties.
+pub fn warnings(value: &Value, propagation: Propagation) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let (mean, sigma) = match value {
+        Value::PosNumber(x) | Value::Number(x) => (*x, 0.0),
+        Value::Rational(x) => (x.to_f64(), 0.0),
+        Value::Measurement(m) => (m.mean, m.sigma),
+        Value::Labeled(m) => (m.mean(), m.sigma()),
+        Value::Comparison(_) => return warnings,
+    };
+    if !mean.is_finite() || !sigma.is_finite() {
+        warnings.push("the result is not a finite number".to_string());
+    } else if sigma > LARGE_RELATIVE_UNCERTAINTY * mean.abs() {
+        let order = match propagation {
+            Propagation::Taylor(Order::First) => "first",
+            Propagation::Taylor(Order::Second) => "second",
+            Propagation::MonteCarlo => return warnings,
+        };
+        warnings.push(format!(
+            "the relative uncertainty is above {}%, so {}-order propagation may be inaccurate",
+            100.0 * LARGE_RELATIVE_UNCERTAINTY,
+            order
+        ));
+    }
+    warnings
+}
+
+///Serializes the outcome of evaluating `expression`, with uncertainties propagated as given
+pub fn to_json(expression: &str, result: &Result<Value, Error>, propagation: Propagation) -> String {
+    let head = format!("{{\"schema\":{},\"expression\":{}", SCHEMA, string(expression));
+    match result {
+        Ok(value) => format!(
+            "{},\"status\":\"ok\",\"result\":{},\"warnings\":[{}]}}",
+            head,
+            value_to_json(value),
+            warnings(value, propagation).iter().map(|w| string(w)).collect::<Vec<String>>().join(",")
+        ),
+        Err(e) => {
+            let span = match e.span {
+                Some(span) => format!("{{\"start\":{},\"end\":{}}}", span.start, span.end),
+                None => "null".to_string(),
+            };
+            format!(
+                "{},\"status\":\"error\",\"error\":{{\"message\":{},\"span\":{}}},\"warnings\":[]}}",
+                head,
+                string(&e.message),
+                span
+            )
+        }
+    }
+}
+
+fn value_to_json(value: &Value) -> String {
+    match value {
+        Value::PosNumber(x) | Value::Number(x) => format!(
+            "{{\"kind\":\"number\",\"mean\":{},\"sigma\":0,\"relative\":0,\"dof\":null,\"components\":[]}}",
+            number(*x)
+        ),
+        Value::Rational(x) => format!(
+            "{{\"kind\":\"number\",\"mean\":{},\"sigma\":0,\"relative\":0,\"dof\":null,\"components\":[]}}",
+            number(x.to_f64())
+        ),
+        Value::Measurement(m) => measurement_to_json(&LabeledMeasurement::from(*m)),
+        Value::Labeled(m) => measurement_to_json(m),
+        Value::Comparison(c) => format!(
+            "{{\"kind\":\"comparison\",\"z_score\":{},\"p_value\":{},\"sigmas\":{},\"agrees\":{}}}",
+            number(c.z_score),
+            number(c.p_value),
+            number(c.sigmas),
+            c.agrees()
+        ),
+    }
+}
+
+fn measurement_to_json(m: &LabeledMeasurement) -> String {
+    //Exact zeros have no relative uncertainty, rather than an undefined one
+    let relative = if m.sigma() == 0.0 { 0.0 } else { m.sigma() / m.mean().abs() };
+    let components: Vec<String> = m
+        .components()
+        .iter()
+        .map(|(label, sigma)| format!("{{\"label\":{},\"sigma\":{}}}", string(label), number(*sigma)))
+        .collect();
+    format!(
+        "{{\"kind\":\"measurement\",\"mean\":{},\"sigma\":{},\"relative\":{},\"dof\":{},\"components\":[{}]}}",
+        number(m.mean()),
+        number(m.sigma()),
+        number(relative),
+        number(m.dof()),
+        components.join(",")
+    )
+}
+
+///A JSON number, or null if it is not finite
+fn number(x: f64) -> String {
+    if x.is_finite() {
+        x.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+///A quoted JSON string
+fn string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::try_eval;
+
+    const LINEAR: Propagation = Propagation::Taylor(Order::First);
+
+    #[test]
+    fn test_measurement() {
+        let json = to_json("2 ± 0.1", &try_eval("2 ± 0.1"), LINEAR);
+        assert_eq!(
+            "{\"schema\":1,\"expression\":\"2 ± 0.1\",\"status\":\"ok\",\"result\":{\"kind\":\"measurement\",\
+             \"mean\":2,\"sigma\":0.1,\"relative\":0.05,\"dof\":null,\"components\":[]},\"warnings\":[]}",
+            json
+        );
+        let json = to_json("1 ± 0.1 (stat)", &try_eval("1 ± 0.1 (stat)"), LINEAR);
+        assert!(json.contains("\"components\":[{\"label\":\"stat\",\"sigma\":0.1}]"));
+        let json = to_json("mean(1, 2)", &try_eval("mean(1, 2)"), LINEAR);
+        assert!(json.contains("\"dof\":1"));
+        let json = to_json("0 ± 0", &try_eval("0 ± 0"), LINEAR);
+        assert!(json.contains("\"mean\":0,\"sigma\":0,\"relative\":0,"));
+    }
+
+    #[test]
+    fn test_number_and_comparison() {
+        let json = to_json("1 / 4", &try_eval("1 / 4"), LINEAR);
+        assert!(json.contains("\"result\":{\"kind\":\"number\",\"mean\":0.25,\"sigma\":0,"));
+        let json = to_json("1 ± 0.1 ~= 1.3", &try_eval("1 ± 0.1 ~= 1.3"), LINEAR);
+        assert!(json.contains("\"kind\":\"comparison\",\"z_score\":-3"));
+        assert!(json.contains("\"sigmas\":2,\"agrees\":false}"));
+    }
+
+    #[test]
+    fn test_error() {
+        let json = to_json("2 * \"x\"", &try_eval("2 * x"), LINEAR);
+        assert_eq!(
+            "{\"schema\":1,\"expression\":\"2 * \\\"x\\\"\",\"status\":\"error\",\"error\":\
+             {\"message\":\"unknown variable: 'x'\",\"span\":{\"start\":4,\"end\":5}},\"warnings\":[]}",
+            json
+        );
+    }
+
+    #[test]
+    fn test_warnings() {
+        assert_eq!(1, warnings(&try_eval("1 ± 0.8").unwrap(), LINEAR).len());
+        assert!(warnings(&try_eval("1 ± 0.1").unwrap(), LINEAR).is_empty());
+        assert_eq!(vec!["the result is not a finite number"], warnings(&try_eval("1 / 0").unwrap(), LINEAR));
+        let json = to_json("0 ± 1", &try_eval("0 ± 1"), LINEAR);
+        assert!(json.contains("\"relative\":null"));
+        assert!(json.contains("\"warnings\":[\"the relative uncertainty is above 50%, so first-order"));
+        let json = to_json("0 ± 1", &try_eval("0 ± 1"), Propagation::Taylor(Order::Second));
+        assert!(json.contains("so second-order propagation may be inaccurate"));
+        assert!(warnings(&try_eval("1 ± 0.8").unwrap(), Propagation::MonteCarlo).is_empty());
+    }
+}
@@ -0,0 +1,252 @@
+//!Rendering of expressions and results as LaTeX, for pasting into papers
+//!
+//!Expressions become math-mode LaTeX, e.g. `(V ± 0.1) / I^2` is rendered as
+//!`\frac{V \pm 0.1}{I^{2}}`. Results can be written in plain math mode, or with the
+//!macros of the `siunitx` package: `\num{2.70 \pm 0.02}`, or `\SI{2.70(2)}{\metre}`
+//!with a unit and the uncertainty in the concise parenthesized form.
+
+use crate::error::Error;
+use crate::arithmetic::Rounding;
+use crate::format::{self, Precision};
+use crate::parser::{parse, S};
+use crate::token::Token;
+use crate::value::Value;
+
+///How results are written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Style {
+    ///Math mode, e.g. `2.70 \pm 0.02`
+    Math,
+    ///The `\num` macro of `siunitx`, e.g. `\num{2.70 \pm 0.02}`
+    Num,
+    ///The `\SI` macro of `siunitx` with the given unit, e.g. `\SI{2.70(2)}{\metre}`
+    Si(String),
+}
+
+///Greek letters which can be written by name, e.g. `sigma` for `\sigma`
+const GREEK: [&str; 24] = [
+    "alpha", "beta", "gamma", "delta", "epsilon", "zeta", "eta", "theta", "iota", "kappa", "lambda", "mu",
+    "nu", "xi", "rho", "sigma", "tau", "upsilon", "phi", "chi", "psi", "omega", "Delta", "Omega",
+];
+
+///Renders the expression as math-mode LaTeX, or returns the error found while parsing it
+pub fn expression(input: &str) -> Result<String, Error> {
+    Ok(render(&parse(input)?))
+}
+
+///Renders a result in the given style, rounded in the given way. Labeled uncertainty
+///components are shown separately in math mode, unless `combined` is set; the `siunitx`
+///macros always show the combined uncertainty.
+pub fn value(value: &Value, style: &Style, precision: Precision, rounding: Rounding, combined: bool) -> String {
+    let text = match (value, style) {
+        (Value::PosNumber(x), _) | (Value::Number(x), _) => format::number(*x, precision, rounding),
+        (Value::Rational(x), _) => format::number(x.to_f64(), precision, rounding),
+        (Value::Labeled(m), Style::Math) if !combined => {
+            let (mut text, _, components) = format::components(m, precision, rounding);
+            for (label, sigma) in components {
+                text += &format!(" \\pm {}", sigma);
+                if !label.is_empty() {
+                    text += &format!("_{{{}}}", upright(label));
+                }
+            }
+            return text;
+        }
+        //Combined in quadrature, as always with the macros of siunitx
+        (Value::Labeled(m), _) => return self::value(&Value::Measurement(m.combined()), style, precision, rounding, combined),
+        (Value::Measurement(m), Style::Si(_)) => {
+            let (mean, sigma) = format::measurement(m.mean, m.sigma, precision, rounding);
+            concise(&mean, &sigma)
+        }
+        (Value::Measurement(m), _) => {
+            let (mean, sigma) = format::measurement(m.mean, m.sigma, precision, rounding);
+            let (mean, sigma) = pad(&mean, &sigma);
+            format!("{} \\pm {}", mean, sigma)
+        }
+        (Value::Comparison(c), _) => {
+            return format!("z = {},\\ p = {}", format::number(c.z_score, precision, rounding), format::number(c.p_value, precision, rounding))
+        }
+    };
+    match style {
+        Style::Math => text,
+        Style::Num => format!("\\num{{{}}}", text),
+        Style::Si(unit) => format!("\\SI{{{}}}{{{}}}", text, unit),
+    }
+}
+
+///Pads the mean and the uncertainty with trailing zeros to the same number of decimals,
+///e.g. `2.7` and `0.0234` as `2.7000` and `0.0234`
+fn pad(mean: &str, sigma: &str) -> (String, String) {
+    let decimals = |x: &str| x.split_once('.').map(|(_, d)| d.len()).unwrap_or(0);
+    let places = decimals(mean).max(decimals(sigma));
+    let pad = |x: &str| {
+        let missing = places - decimals(x);
+        match (missing, x.contains('.')) {
+            (0, _) => x.to_string(),
+            (_, true) => format!("{}{}", x, "0".repeat(missing)),
+            (_, false) => format!("{}.{}", x, "0".repeat(missing)),
+        }
+    };
+    (pad(mean), pad(sigma))
+}
+
+///Writes a mean and an uncertainty in the concise form, e.g. `2.70(2)`: the digits
+///in parentheses are the uncertainty in units of the last digit of the mean
+fn concise(mean: &str, sigma: &str) -> String {
+    let (mean, sigma) = pad(mean, sigma);
+    let digits = sigma.replace('.', "");
+    let digits = match digits.trim_start_matches('0') {
+        "" => "0",
+        digits => digits,
+    };
+    format!("{}({})", mean, digits)
+}
+
+///Binding power of the outermost operator, used to decide where parentheses are needed
+fn precedence(s: &S) -> u8 {
+    match s {
+        S::Group(Token::Approx, _, _) => 0,
+        S::Group(Token::Add, _, _) => 1,
+        S::Group(Token::Minus, operands, _) if operands.len() == 2 => 1,
+        S::Group(Token::PlusMinus, _, _) => 2,
+        S::Group(Token::Minus, _, _) => 3,
+        S::Group(Token::Mul, _, _) | S::Group(Token::Div, _, _) => 4,
+        S::Group(Token::Caret, _, _) => 5,
+        _ => 6,
+    }
+}
+
+///Renders an operand, in parentheses if it binds less tightly than `min`.
+///Measurements are always in parentheses, so that `(1 ± 0.1) * 2` doesn't read as `1 ± 0.2`.
+fn operand(s: &S, min: u8) -> String {
+    if precedence(s) < min || matches!(s, S::Group(Token::PlusMinus, _, _)) {
+        format!("\\left({}\\right)", render(s))
+    } else {
+        render(s)
+    }
+}
+
+///Writes text in upright letters, e.g. `sys_a` as `\mathrm{sys\_a}`
+fn upright(text: &str) -> String {
+    format!("\\mathrm{{{}}}", text.replace('_', "\\_"))
+}
+
+///Renders a name, e.g. `x_1` as `x_{1}`, `sigma` as `\sigma` and `slope` as `\mathrm{slope}`.
+///Further underscores nest the subscripts: `x_1_2` is `x_{1_{2}}`.
+fn name(name: &str) -> String {
+    let word = |w: &str| {
+        if GREEK.contains(&w) {
+            format!("\\{}", w)
+        } else if w.chars().count() == 1 {
+            w.to_string()
+        } else {
+            upright(w)
+        }
+    };
+    match name.split_once('_') {
+        Some((base, subscript)) if !base.is_empty() && !subscript.is_empty() => {
+            format!("{}_{{{}}}", word(base), self::name(subscript))
+        }
+        _ => word(name),
+    }
+}
+
+///Renders a number, writing scientific notation with a power of ten, e.g. `7.8800e4`
+///as `7.8800 \times 10^{4}`
+fn number(text: &str) -> String {
+    match text.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => {
+            format!("{} \\times 10^{{{}}}", mantissa, exponent.trim_start_matches('+'))
+        }
+        None => text.to_string(),
+    }
+}
+
+fn render(s: &S) -> String {
+    match s {
+        S::Atom(Token::Ident(n), _) => name(n),
+        S::Atom(Token::EulersNum, _) => "e".to_string(),
+        S::Atom(Token::Pi, _) => "\\pi".to_string(),
+        S::Atom(Token::PosNum(x), _) => number(&x.to_string()),
+        S::Atom(t, _) => t.to_string(),
+        S::Group(op, operands, _) => {
+            let list = |items: &[S]| items.iter().map(render).collect::<Vec<String>>().join(", ");
+            match (op, operands.as_slice()) {
+                (Token::Add, [l, r]) => format!("{} + {}", operand(l, 1), operand(r, 2)),
+                (Token::Minus, [l, r]) => format!("{} - {}", operand(l, 1), operand(r, 2)),
+                (Token::Minus, [x]) => format!("-{}", operand(x, 4)),
+                (Token::Mul, [l, r]) => format!("{} \\cdot {}", operand(l, 3), operand(r, 4)),
+                (Token::Div, [l, r]) => format!("\\frac{{{}}}{{{}}}", render(l), render(r)),
+                (Token::Caret, [base, exponent]) => format!("{}^{{{}}}", operand(base, 6), render(exponent)),
+                (Token::Approx, [l, r]) => format!("{} \\approx {}", render(l), render(r)),
+                (Token::PlusMinus, [mean, sigma, rest @ ..]) => {
+                    let mean = match mean {
+                        S::Group(Token::PlusMinus, _, _) => render(mean),
+                        _ => operand(mean, 3),
+                    };
+                    let label = match rest {
+                        [S::Atom(Token::Ident(label), _)] => format!("_{{{}}}", upright(label)),
+                        _ => String::new(),
+                    };
+                    format!("{} \\pm {}{}", mean, operand(sigma, 3), label)
+                }
+                (Token::Ident(function), arguments) => format!("{}\\left({}\\right)", upright(function), list(arguments)),
+                (Token::LeftBracket, items) => format!("\\left[{}\\right]", list(items)),
+                _ => s.to_string(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::eval;
+
+    #[test]
+    fn test_expression() {
+        assert_eq!("\\frac{V}{I^{2}}", expression("V / I^2").unwrap());
+        assert_eq!("\\left(1.0 \\pm 0.1\\right) \\cdot 2", expression("(1.0 ± 0.1) * 2").unwrap());
+        assert_eq!("a - \\left(b + c\\right)", expression("a - (b + c)").unwrap());
+        assert_eq!("\\left(a + b\\right)^{2}", expression("(a + b) ^ 2").unwrap());
+        assert_eq!("-2^{2} \\cdot 3", expression("-2 ^ 2 * 3").unwrap());
+        assert_eq!("2 \\cdot \\pi \\cdot r_{0}", expression("2 * pi * r_0").unwrap());
+        assert_eq!("\\mathrm{mean}\\left(1, \\left[2, 3\\right]\\right)", expression("mean(1, [2, 3])").unwrap());
+        assert_eq!("\\sigma \\cdot \\mathrm{slope}", expression("sigma * slope").unwrap());
+        assert!(expression("1 +").is_err());
+    }
+
+    #[test]
+    fn test_underscores_and_exponents() {
+        assert_eq!("x_{1_{2}}", expression("x_1_2").unwrap());
+        assert_eq!("\\mathrm{slope}_{\\mathrm{fit}_{2}}", expression("slope_fit_2").unwrap());
+        assert_eq!("\\mathrm{max\\_}", expression("max_").unwrap());
+        assert_eq!("7.8800 \\times 10^{4} + 1.5 \\times 10^{-3}", expression("7.8800e4 + 1.5e-3").unwrap());
+        assert_eq!("1 \\pm 0.1_{\\mathrm{sys\\_a}}", expression("1 ± 0.1 (sys_a)").unwrap());
+        let v = eval("1 ± 0.1 (sys_a) ± 0.2 (stat)");
+        assert_eq!("1.0 \\pm 0.1_{\\mathrm{sys\\_a}} \\pm 0.2_{\\mathrm{stat}}", value(&v, &Style::Math, Precision::Decimals(1), Rounding::HalfUp, false));
+    }
+
+    #[test]
+    fn test_labels() {
+        assert_eq!(
+            "12.3 \\pm 0.4_{\\mathrm{stat}} \\pm 0.2_{\\mathrm{syst}}",
+            expression("12.3 ± 0.4 (stat) ± 0.2 (syst)").unwrap()
+        );
+        let v = eval("12.3 ± 0.4 (stat) ± 0.2 (syst)");
+        assert_eq!("12.3 \\pm 0.4_{\\mathrm{stat}} \\pm 0.2_{\\mathrm{syst}}", value(&v, &Style::Math, Precision::Decimals(1), Rounding::HalfUp, false));
+        assert_eq!("12.3 \\pm 0.4", value(&v, &Style::Math, Precision::Decimals(1), Rounding::HalfUp, true));
+    }
+
+    #[test]
+    fn test_siunitx() {
+        let v = eval("2.7 ± 0.0234");
+        assert_eq!("\\num{2.70 \\pm 0.02}", value(&v, &Style::Num, Precision::SigFigs(1), Rounding::HalfUp, false));
+        assert_eq!("\\SI{2.70(2)}{\\metre}", value(&v, &Style::Si("\\metre".into()), Precision::SigFigs(1), Rounding::HalfUp, false));
+        assert_eq!("\\SI{2.7000(234)}{\\metre}", value(&v, &Style::Si("\\metre".into()), Precision::Full, Rounding::HalfUp, false));
+        assert_eq!("\\num{2.7000 \\pm 0.0234}", value(&v, &Style::Num, Precision::Full, Rounding::HalfUp, false));
+        assert_eq!("\\SI{2.7123(200)}{\\metre}", value(&eval("2.7123 ± 0.02"), &Style::Si("\\metre".into()), Precision::Full, Rounding::HalfUp, false));
+        assert_eq!("\\SI{3.0(5)}{\\metre}", value(&eval("3 ± 0.5"), &Style::Si("\\metre".into()), Precision::Full, Rounding::HalfUp, false));
+        assert_eq!("\\SI{12300(200)}{\\volt}", value(&eval("12345 ± 234"), &Style::Si("\\volt".into()), Precision::SigFigs(1), Rounding::HalfUp, false));
+        assert_eq!("\\num{0.25}", value(&eval("1 / 4"), &Style::Num, Precision::Full, Rounding::HalfUp, false));
+    }
+}
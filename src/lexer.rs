@@ -1,38 +1,64 @@
 use crate::decimal::DecimalNumber;
+use crate::error::{Error, Span};
 use std::{iter::Peekable, str::Chars};
 
 use crate::token::Token;
 
 struct Scanner<'a> {
     characters: Peekable<Chars<'a>>,
+    position: usize, //Number of characters read so far
 }
 
 impl<'a> Scanner<'_> {
     fn new(input: &'a str) -> Scanner<'a> {
         let characters = input.chars().peekable();
 
-        Scanner { characters }
+        Scanner { characters, position: 0 }
     }
 
     fn next(&mut self) -> Option<char> {
-        self.characters.next()
+        let c = self.characters.next();
+        if c.is_some() {
+            self.position += 1;
+        }
+        c
     }
 
     fn peek(&mut self) -> Option<char> {
         self.characters.peek().copied()
     }
+
+    ///Returns the n-th next character without consuming anything.
+    ///`peek_nth(0)` is the same as `peek()`
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.characters.clone().nth(n)
+    }
 }
 pub struct Lexer {
     tokens: Vec<Token>,
+    spans: Vec<Span>, //Where each token is in the input
 }
 
 impl Lexer {
+    ///Splits the input into tokens.
+    ///
+    ///Panics on invalid input, see `try_new`.
     pub fn new(input: &str) -> Lexer {
+        match Lexer::try_new(input) {
+            Ok(lexer) => lexer,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    ///Splits the input into tokens, or fails at the first invalid character
+    pub fn try_new(input: &str) -> Result<Lexer, Error> {
         let mut scanner = Scanner::new(input);
         let mut opt_c: Option<char>;
         let mut c: char;
         let mut tokens: Vec<Token> = Vec::new();
+        let mut spans: Vec<Span> = Vec::new();
         loop {
+            let start = scanner.position;
             opt_c = scanner.peek();
 
             match opt_c {
@@ -42,37 +68,57 @@ impl Lexer {
                 }
                 None => {
                     tokens.push(Token::Eof); //EOF - termination point
+                    spans.push(Span::new(start, start));
                     break;
                 }
             };
 
             let opt_token: Option<Token> = match c {
+                //'+/-' stands for '±', which is awkward to type in a shell. Not '+-', since '3+-1' is 3 + (-1)
+                '+' if scanner.peek() == Some('/') && scanner.peek_nth(1) == Some('-') => {
+                    scanner.next();
+                    scanner.next();
+                    Some(Token::PlusMinus)
+                },
                 '+' => Some(Token::Add),
                 '-' => Some(Token::Minus),
                 '*' => Some(Token::Mul),
                 '/' => Some(Token::Div),
+                '^' => Some(Token::Caret),
                 '±' => Some(Token::PlusMinus),
-                'e' => Some(Token::EulersNum),
+                '~' => match scanner.next() {
+                    Some('=') => Some(Token::Approx),
+                    _ => return Err(Error::new("Unexpected character: \'~\', did you mean \'~=\'?", Span::new(start, start + 1))),
+                },
                 'π' => Some(Token::Pi),
                 '(' => Some(Token::LeftParen),
                 ')' => Some(Token::RightParen),
-                '0'..='9' => Some(Lexer::parse_number(c, false, &mut scanner)),
-                '.' => Some(Lexer::parse_number(c, true, &mut scanner)),
+                '[' => Some(Token::LeftBracket),
+                ']' => Some(Token::RightBracket),
+                ',' => Some(Token::Comma),
+                '0'..='9' => Some(Lexer::parse_number(c, false, &mut scanner)?),
+                '.' => Some(Lexer::parse_number(c, true, &mut scanner)?),
+                c if c.is_alphabetic() || c == '_' => Some(Lexer::parse_identifier(c, &mut scanner)),
                 ' ' | '\t' | '\n' => continue, //whitespace
-                _ => panic!("Unexpected character: \'{}\'", c),
+                _ => return Err(Error::new(format!("Unexpected character: \'{}\'", c), Span::new(start, start + 1))),
             };
-            match opt_token {
-                Some(t) => tokens.push(t),
-                None => {}
+            if let Some(t) = opt_token {
+                tokens.push(t);
+                spans.push(Span::new(start, scanner.position));
             }
         }
 
         tokens.reverse();
+        spans.reverse();
 
-        Lexer { tokens }
+        Ok(Lexer { tokens, spans })
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Token {
+        if self.tokens.len() > 1 {
+            self.spans.pop();
+        }
         self.tokens.pop().unwrap_or(Token::Eof)
     }
 
@@ -80,7 +126,45 @@ impl Lexer {
         self.tokens.last().cloned().unwrap_or(Token::Eof)
     }
 
-    fn parse_number(init_c: char, mut found_period: bool, scanner: &mut Scanner) -> Token {
+    ///Where the next token is in the input
+    pub fn span(&self) -> Span {
+        self.spans.last().copied().unwrap_or_else(|| Span::new(0, 0))
+    }
+
+    ///Returns the n-th next token without consuming anything.
+    ///`peek_nth(0)` is the same as `peek()`
+    pub fn peek_nth(&self, n: usize) -> Token {
+        if n < self.tokens.len() {
+            self.tokens[self.tokens.len() - 1 - n].clone()
+        } else {
+            Token::Eof
+        }
+    }
+
+    ///Reads a name, which may contain letters, digits and underscores
+    ///
+    ///The name 'e' is reserved for Euler's number, and 'pi' is a digraph for 'π'
+    fn parse_identifier(init_c: char, scanner: &mut Scanner) -> Token {
+        let mut name = String::from(init_c);
+
+        while let Some(c) = scanner.peek() {
+            if c.is_alphanumeric() || c == '_' {
+                name.push(c);
+                scanner.next();
+            } else {
+                break;
+            }
+        }
+
+        match name.as_str() {
+            "e" => Token::EulersNum,
+            "pi" => Token::Pi,
+            _ => Token::Ident(name),
+        }
+    }
+
+    fn parse_number(init_c: char, mut found_period: bool, scanner: &mut Scanner) -> Result<Token, Error> {
+        let start = scanner.position - 1;
         let mut number_str = String::from("");
         let mut opt_c: Option<char>;
         let mut c: char;
@@ -119,17 +203,28 @@ impl Lexer {
             scanner.next();
         }
 
-        match number_str.chars().last() {
-            Some(val) => {
-                if val == '.' {
-                    panic!("Error: numeric literal cannot end in a period. Problematic literal: \"{}\"", number_str);
+        //Scientific notation, e.g. '7.8800e4' or '1.5E-3'. Without digits after it,
+        //the 'e' is not an exponent but a name, such as Euler's number.
+        let digit = |c: Option<char>| c.is_some_and(|c| c.is_ascii_digit());
+        if let Some(e @ ('e' | 'E')) = scanner.peek() {
+            let sign = scanner.peek_nth(1).filter(|c| *c == '+' || *c == '-');
+            if digit(scanner.peek_nth(if sign.is_some() { 2 } else { 1 })) {
+                number_str.push(e);
+                scanner.next();
+                if let Some(sign) = sign {
+                    number_str.push(sign);
+                    scanner.next();
+                }
+                while digit(scanner.peek()) {
+                    number_str.extend(scanner.next());
                 }
             }
-            None => (),
-        };
-        let number = DecimalNumber::new(number_str.as_str());
+        }
 
-        Token::PosNum(number)
+        match number_str.parse::<DecimalNumber>() {
+            Ok(number) => Ok(Token::PosNum(number)),
+            Err(e) => Err(Error::new(e.message, Span::new(start, scanner.position))),
+        }
     }
 }
 
@@ -206,9 +301,15 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
     fn test_float_4() {
-        Lexer::new("23.");
+        //A trailing period makes the trailing zeros significant
+        let mut lex = Lexer::new("23.");
+        num_eq("23.", lex.next());
+        assert_eq!(Token::Eof, lex.next());
+        match Lexer::new("78800.").next() {
+            Token::PosNum(x) => assert_eq!(5, x.sig_figs()),
+            _ => panic!("Error"),
+        }
     }
 
     #[test]
@@ -219,7 +320,20 @@ mod tests {
     #[test]
     #[should_panic]
     fn test_float_6() {
-        Lexer::new("2 + 5 - 33.");
+        Lexer::new("2 + 5 - 33..");
+    }
+
+    #[test]
+    fn test_scientific_notation() {
+        let mut lex = Lexer::new("7.8800e4 * 1.5E-3");
+        num_eq("7.8800e4", lex.next());
+        assert_eq!(Token::Mul, lex.next());
+        num_eq("1.5e-3", lex.next());
+        assert_eq!(Token::Eof, lex.next());
+        let mut lex = Lexer::new("2e+x");
+        num_eq("2", lex.next());
+        assert_eq!(Token::EulersNum, lex.next());
+        assert_eq!(Token::Add, lex.next());
     }
 
     #[test]
@@ -236,6 +350,89 @@ mod tests {
         assert_eq!(Token::Eof, lex.next());
     }
 
+    #[test]
+    fn test_digraphs() {
+        let mut lex = Lexer::new("2.3+/-0.1 + pi");
+        num_eq("2.3", lex.next());
+        assert_eq!(Token::PlusMinus, lex.next());
+        num_eq("0.1", lex.next());
+        assert_eq!(Token::Add, lex.next());
+        assert_eq!(Token::Pi, lex.next());
+        assert_eq!(Token::Eof, lex.next());
+
+        let mut lex = Lexer::new("3+-1");
+        num_eq("3", lex.next());
+        assert_eq!(Token::Add, lex.next());
+        assert_eq!(Token::Minus, lex.next());
+        num_eq("1", lex.next());
+        assert_eq!(Token::Eof, lex.next());
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let mut lex = Lexer::new("slope * x_1 + e");
+        assert_eq!(Token::Ident("slope".into()), lex.next());
+        assert_eq!(Token::Mul, lex.next());
+        assert_eq!(Token::Ident("x_1".into()), lex.next());
+        assert_eq!(Token::Add, lex.next());
+        assert_eq!(Token::EulersNum, lex.next());
+        assert_eq!(Token::Eof, lex.next());
+    }
+
+    #[test]
+    fn test_lists() {
+        let mut lex = Lexer::new("mean([1, 2])");
+        assert_eq!(Token::Ident("mean".into()), lex.next());
+        assert_eq!(Token::LeftParen, lex.next());
+        assert_eq!(Token::LeftBracket, lex.next());
+        num_eq("1", lex.next());
+        assert_eq!(Token::Comma, lex.next());
+        num_eq("2", lex.next());
+        assert_eq!(Token::RightBracket, lex.next());
+        assert_eq!(Token::RightParen, lex.next());
+        assert_eq!(Token::Eof, lex.next());
+    }
+
+    #[test]
+    fn test_approx() {
+        let mut lex = Lexer::new("x~=2");
+        assert_eq!(Token::Ident("x".into()), lex.next());
+        assert_eq!(Token::Approx, lex.next());
+        num_eq("2", lex.next());
+        assert_eq!(Token::Eof, lex.next());
+    }
+
+    #[test]
+    fn test_spans() {
+        let mut lex = Lexer::new("2 ± 0.15*x");
+        assert_eq!(Span::new(0, 1), lex.span());
+        lex.next();
+        assert_eq!(Span::new(2, 3), lex.span());
+        lex.next();
+        assert_eq!(Span::new(4, 8), lex.span());
+        lex.next();
+        lex.next();
+        assert_eq!(Span::new(9, 10), lex.span());
+        lex.next();
+        assert_eq!(Span::new(10, 10), lex.span());
+        lex.next();
+        assert_eq!(Span::new(10, 10), lex.span());
+
+        let error = Lexer::try_new("1 + $").err().unwrap();
+        assert_eq!(Some(Span::new(4, 5)), error.span);
+        assert_eq!(Some(Span::new(4, 5)), Lexer::try_new("1 + .").err().unwrap().span);
+    }
+
+    #[test]
+    fn test_peek_nth() {
+        let mut lex = Lexer::new("0.4 (stat)");
+        assert_eq!(Token::LeftParen, lex.peek_nth(1));
+        assert_eq!(Token::Ident("stat".into()), lex.peek_nth(2));
+        assert_eq!(Token::Eof, lex.peek_nth(4));
+        num_eq("0.4", lex.next());
+        assert_eq!(Token::LeftParen, lex.peek());
+    }
+
     #[test]
     fn test_eof() {
         let mut lex = Lexer::new("");
@@ -1,6 +1,20 @@
 pub mod measurement;
 pub mod parser;
 pub mod decimal;
+pub mod environment;
 pub mod token;
 pub mod lexer;
-pub mod value;
\ No newline at end of file
+pub mod propagation;
+pub mod value;
+pub mod error;
+pub mod statistics;
+pub mod fitting;
+pub mod batch;
+pub mod script;
+pub mod json;
+pub mod montecarlo;
+pub mod format;
+pub mod latex;
+pub mod arithmetic;
+pub mod rational;
+pub mod sigfigs;
@@ -1,32 +1,577 @@
-use std::{env, panic};
+use std::io::{IsTerminal, Read};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs, io, process};
 
-use scicalc_rs::parser::eval;
+use scicalc_rs::arithmetic::{self, Context, Rounding};
+use scicalc_rs::batch;
+use scicalc_rs::environment::Environment;
+use scicalc_rs::error::{Error, Span};
+use scicalc_rs::format::{self, Precision};
+use scicalc_rs::json;
+use scicalc_rs::latex::{self, Style};
+use scicalc_rs::measurement::Measurement;
+use scicalc_rs::montecarlo;
+use scicalc_rs::propagation::Order;
+use scicalc_rs::script::{self, split_assignment};
+use scicalc_rs::sigfigs;
+use scicalc_rs::value::Value;
+
+///Exit code when an expression, a definition or a script fails
+const EXIT_FAILURE: i32 = 1;
+///Exit code when the command line itself is wrong
+const EXIT_USAGE: i32 = 2;
 
 fn show_usage() {
-    println!("Usage: scicalc-rs [expression]");
+    println!("Usage: scicalc-rs [options] <expression>...");
+    println!("       scicalc-rs [options] --csv <file> [name=]<expression>");
+    println!("       scicalc-rs [options] --script <file>");
+    println!();
+    println!("Evaluates expressions with uncertainties, e.g. '(2.0 ± 0.1) * 3'. Since '±' and 'π' are");
+    println!("awkward to type in a shell, '+/-' and 'pi' can be used instead: scicalc-rs '2.0 +/- 0.1'");
+    println!();
+    println!("Options:");
+    println!("  -h, --help                Print this help and exit");
+    println!("  -V, --version             Print the version and exit");
+    println!("  --mode linear|montecarlo  Propagate uncertainties with a Taylor expansion (default),");
+    println!("                            or by simulating random draws of the measurements");
+    println!("  --second-order            Use a second-order Taylor expansion in linear mode");
+    println!("  --arithmetic float|decimal|sigfigs");
+    println!("                            Compute means in floating point (default), exactly with");
+    println!("                            decimal arithmetic, so that 0.1 + 0.2 is 0.3, or with the");
+    println!("                            significant-figure rules of lab courses, e.g. 2.50 * 1.2 = 3.0");
+    println!("  --digits n                Significant digits of decimal arithmetic (default {})", arithmetic::DEFAULT_PRECISION);
+    println!("  --rounding mode           Rounding of the printed results: half-even, half-up or");
+    println!("                            toward-zero. The default is half-up with --arithmetic float,");
+    println!("                            and half-even with decimal or sigfigs");
+    println!("  --runs n                  Number of Monte Carlo runs (default {})", montecarlo::DEFAULT_RUNS);
+    println!("  --seed n                  Seed of the Monte Carlo runs, to make them reproducible");
+    println!("  --precision n             Print results with n decimal places");
+    println!("  --sigfigs n               Print uncertainties with n significant figures, and");
+    println!("                            means rounded to the same decimal place");
+    println!("  --combined                Add labeled uncertainty components in quadrature");
+    println!("  --fractions               Print exact results of integer arithmetic as fractions, e.g. 2/3");
+    println!("  --confidence level        Also print the expanded uncertainty at a confidence");
+    println!("                            level such as 0.95 or 95%");
+    println!("  --sigmas n                Judge comparisons such as 'a ~= b' at n sigmas instead of 2");
+    println!("  --format text|json|latex  Print results as text (default), as JSON objects, or as");
+    println!("                            LaTeX equations such as '\\frac{{a}}{{b}} = 2.70 \\pm 0.02'");
+    println!("  --siunitx                 Write LaTeX results with \\num{{...}} from the siunitx package");
+    println!("  --unit unit               Write LaTeX results as \\SI{{2.70(2)}}{{unit}}, e.g. --unit '\\metre'");
+    println!("  --define name=expression  Define a variable for the expressions (repeatable)");
+    println!("  --csv file                Evaluate the expression for every row of the file ('-' for");
+    println!("                            stdin), whose columns are variables (X ± sigma_X), and print");
+    println!("                            the table with the result's columns");
+    println!("  -f, --script file         Run the script in the file ('-' for stdin)");
+    println!();
+    println!("With more than one expression, their correlation and covariance matrices are printed,");
+    println!("without the expanded uncertainties, fractions or labeled components of single results.");
+    println!("A script has an assignment (name = expression) or an expression to print on each line,");
+    println!("and '#' starts a comment. With '-' or without arguments, the script is read from stdin.");
+    println!("A file is only run as a script with --script, so that expressions are never taken for files.");
+    println!();
+    println!("Exit status: 0 on success, {} if an expression fails, {} on bad usage.", EXIT_FAILURE, EXIT_USAGE);
 }
-fn main() {
-    panic::set_hook(Box::new(|_info| {
-        // do nothing
-        //TODO: Add proper error handling
-    }));
-
-
-    let args: Vec<String> = env::args().collect();
-    if args.len() <= 1 {
-        println!("Error: Not enough args.");
-        show_usage();
-    } else if args.len() == 2 {
-        let result = panic::catch_unwind(|| {
-            let text = args[1].as_str();
-            eval(text)
-        });
-        match result {
-            Ok(res) => println!("{}", res),
-            Err(_) => println!("Error: could not parse expression."),
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Json,
+    Latex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Linear,
+    MonteCarlo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Arithmetic {
+    Float,
+    Decimal,
+    SigFigs,
+}
+
+struct Options {
+    mode: Mode,
+    order: Order,
+    arithmetic: Arithmetic,
+    context: Context, //Precision and rounding of decimal arithmetic
+    runs: usize,
+    seed: Option<u64>,
+    precision: Precision,
+    combined: bool,
+    fractions: bool, //Print exact results as fractions rather than decimals
+    confidence: Option<f64>,
+    sigmas: Option<f64>,
+    format: Format,
+    style: Style, //How LaTeX results are written
+    csv: Option<String>,
+    script: Option<String>, //File of the script to run, '-' for stdin
+    definitions: Vec<String>,
+    expressions: Vec<String>,
+}
+
+///Parses a confidence level such as '0.95', '95' or '95%'
+fn parse_confidence(text: &str) -> Option<f64> {
+    let (number, percent) = match text.strip_suffix('%') {
+        Some(number) => (number, true),
+        None => (text, false),
+    };
+    let level: f64 = number.trim().parse().ok()?;
+    let level = if percent || level >= 1.0 { level / 100.0 } else { level };
+    if level > 0.0 && level < 1.0 {
+        Some(level)
+    } else {
+        None
+    }
+}
+
+///Parses the command line. Returns no options if it only asked for the help or the version,
+///which are printed right away.
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Options>, String> {
+    let mut options = Options {
+        mode: Mode::Linear,
+        order: Order::First,
+        arithmetic: Arithmetic::Float,
+        context: Context::default(),
+        runs: montecarlo::DEFAULT_RUNS,
+        seed: None,
+        precision: Precision::Full,
+        combined: false,
+        fractions: false,
+        confidence: None,
+        sigmas: None,
+        format: Format::Text,
+        style: Style::Math,
+        csv: None,
+        script: None,
+        definitions: Vec::new(),
+        expressions: Vec::new(),
+    };
+    let mut precision: Option<(&str, Precision)> = None;
+    let mut rounding: Option<Rounding> = None;
+    while let Some(arg) = args.next() {
+        let mut value = |expected: &str| args.next().ok_or(format!("{} expects {}", arg, expected));
+        match arg.as_str() {
+            "-h" | "--help" => {
+                show_usage();
+                return Ok(None);
+            }
+            "-V" | "--version" => {
+                println!("scicalc-rs {}", env!("CARGO_PKG_VERSION"));
+                return Ok(None);
+            }
+            "--mode" => match value("linear or montecarlo")?.as_str() {
+                "linear" => options.mode = Mode::Linear,
+                "montecarlo" => options.mode = Mode::MonteCarlo,
+                _ => return Err("--mode expects linear or montecarlo".into()),
+            },
+            "--second-order" => options.order = Order::Second,
+            "--arithmetic" => match value("float, decimal or sigfigs")?.as_str() {
+                "float" => options.arithmetic = Arithmetic::Float,
+                "decimal" => options.arithmetic = Arithmetic::Decimal,
+                "sigfigs" => options.arithmetic = Arithmetic::SigFigs,
+                _ => return Err("--arithmetic expects float, decimal or sigfigs".into()),
+            },
+            "--digits" => match value("a number of digits")?.parse() {
+                Ok(digits) if digits > 0 => options.context.precision = digits,
+                _ => return Err("--digits expects a number of significant digits, at least 1".into()),
+            },
+            "--rounding" => match value("a rounding mode")?.as_str() {
+                "half-even" => rounding = Some(Rounding::HalfEven),
+                "half-up" => rounding = Some(Rounding::HalfUp),
+                "toward-zero" => rounding = Some(Rounding::TowardZero),
+                _ => return Err("--rounding expects half-even, half-up or toward-zero".into()),
+            },
+            "--runs" => match value("a number of runs")?.parse() {
+                Ok(runs) if runs >= 2 => options.runs = runs,
+                _ => return Err("--runs expects a number of runs, at least 2".into()),
+            },
+            "--seed" => match value("a non-negative integer")?.parse() {
+                Ok(seed) => options.seed = Some(seed),
+                Err(_) => return Err("--seed expects a non-negative integer".into()),
+            },
+            "--precision" | "--sigfigs" => {
+                let digits = value("a number of digits")?.parse().map_err(|_| format!("{} expects a number of digits", arg))?;
+                let (flag, chosen) = match arg.as_str() {
+                    "--precision" => ("--precision", Precision::Decimals(digits)),
+                    _ if digits == 0 => return Err("--sigfigs expects at least one significant figure".into()),
+                    _ => ("--sigfigs", Precision::SigFigs(digits)),
+                };
+                if let Some((other, _)) = precision.filter(|(other, _)| *other != flag) {
+                    return Err(format!("{} and {} cannot be used together", other, flag));
+                }
+                precision = Some((flag, chosen));
+            }
+            "--combined" => options.combined = true,
+            "--fractions" => options.fractions = true,
+            "--confidence" => match parse_confidence(&value("a confidence level")?) {
+                Some(level) => options.confidence = Some(level),
+                None => return Err("--confidence expects a level between 0 and 1, or a percentage".into()),
+            },
+            "--sigmas" => match value("a positive number")?.parse::<f64>() {
+                Ok(n) if n > 0.0 => options.sigmas = Some(n),
+                _ => return Err("--sigmas expects a positive number".into()),
+            },
+            "--format" => match value("text, json or latex")?.as_str() {
+                "text" => options.format = Format::Text,
+                "json" => options.format = Format::Json,
+                "latex" => options.format = Format::Latex,
+                _ => return Err("--format expects text, json or latex".into()),
+            },
+            "--siunitx" => {
+                if options.style == Style::Math {
+                    options.style = Style::Num;
+                }
+            }
+            "--unit" => options.style = Style::Si(value("a siunitx unit")?),
+            "--csv" => options.csv = Some(value("a file")?),
+            "-f" | "--script" => options.script = Some(value("a file")?),
+            "--define" => {
+                let definition = value("name=expression")?;
+                if split_assignment(&definition).0.is_none() {
+                    return Err(format!("--define expects name=expression, got '{}'", definition));
+                }
+                options.definitions.push(definition);
+            }
+            "--" => options.expressions.extend(args.by_ref()),
+            _ if arg.starts_with("--") => return Err(format!("unknown option: {}", arg)),
+            _ => options.expressions.push(arg),
+        }
+    }
+    if let Some((_, chosen)) = precision {
+        options.precision = chosen;
+    }
+    if options.script.is_none() && options.csv.is_none() && options.expressions == ["-"] {
+        options.script = options.expressions.pop();
+    }
+    if options.script.is_some() && (options.csv.is_some() || !options.expressions.is_empty()) {
+        return Err("--script cannot be used with --csv or expressions".into());
+    }
+    if options.mode == Mode::MonteCarlo {
+        if options.order == Order::Second {
+            return Err("--second-order only applies to --mode linear".into());
+        }
+        if options.csv.is_some() {
+            return Err("--csv only supports --mode linear".into());
+        }
+    }
+    if options.confidence.is_some() && (options.format != Format::Text || options.csv.is_some()) {
+        return Err("--confidence only applies to --format text, without --csv".into());
+    }
+    //The table of several results only has their means, uncertainties and correlations
+    if options.format == Format::Text && options.csv.is_none() && options.expressions.len() > 1 {
+        let flags = [("--confidence", options.confidence.is_some()), ("--fractions", options.fractions), ("--combined", options.combined)];
+        if let Some((flag, _)) = flags.iter().find(|(_, given)| *given) {
+            return Err(format!("{} only applies to a single expression or a script", flag));
+        }
+    }
+    if options.arithmetic != Arithmetic::Float {
+        let name = if options.arithmetic == Arithmetic::Decimal { "decimal" } else { "sigfigs" };
+        if options.mode == Mode::MonteCarlo || options.order == Order::Second {
+            return Err(format!("--arithmetic {} only supports first-order --mode linear", name));
+        }
+        if options.format != Format::Text || options.csv.is_some() {
+            return Err(format!("--arithmetic {} only supports --format text, without --csv", name));
+        }
+        if options.expressions.len() > 1 {
+            return Err(format!("--arithmetic {} expects a single expression", name));
+        }
+    }
+    if options.arithmetic != Arithmetic::Decimal && options.context.precision != arithmetic::DEFAULT_PRECISION {
+        return Err("--digits only applies to --arithmetic decimal".into());
+    }
+    //Floating-point results are printed rounded half away from zero, unless asked otherwise
+    options.context.rounding = match (rounding, options.arithmetic) {
+        (Some(rounding), _) => rounding,
+        (None, Arithmetic::Float) => Rounding::HalfUp,
+        (None, _) => Rounding::HalfEven,
+    };
+    if options.fractions && (options.format != Format::Text || options.arithmetic != Arithmetic::Float) {
+        return Err("--fractions only applies to --format text with --arithmetic float".into());
+    }
+    if options.style != Style::Math && options.format != Format::Latex {
+        return Err("--siunitx and --unit only apply to --format latex".into());
+    }
+    if options.csv.is_some() && options.expressions.len() != 1 {
+        return Err("--csv expects a single expression".into());
+    }
+    Ok(Some(options))
+}
+
+///Reports a usage error and exits
+fn usage_error(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    eprintln!("Try 'scicalc-rs --help' for more information.");
+    process::exit(EXIT_USAGE);
+}
+
+///Reports an error in an expression, pointing at where it was found, and exits
+fn fail(expression: &str, error: &Error) -> ! {
+    eprintln!("error: {}", error.message);
+    if let Some(span) = error.span {
+        eprintln!("  {}", expression);
+        eprintln!("  {}{}", " ".repeat(span.start), "^".repeat((span.end - span.start).max(1)));
+    }
+    process::exit(EXIT_FAILURE);
+}
+
+///Reads a file, or stdin for '-'
+fn read_input(path: &str) -> String {
+    let contents = if path == "-" {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents).map(|_| contents)
+    } else {
+        fs::read_to_string(path)
+    };
+    match contents {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("error: could not read {}: {}", path, e);
+            process::exit(EXIT_FAILURE);
+        }
+    }
+}
+
+///Formats a result as text, writing exact fractions such as 2/3 as such with --fractions
+fn text_value(value: &Value, options: &Options) -> String {
+    match value {
+        Value::Rational(x) if options.fractions && !x.is_integer() => x.to_string(),
+        _ => format::value(value, options.precision, options.context.rounding, options.combined),
+    }
+}
+
+///Reads a script from a file (or stdin) and runs it, exiting with an error
+///code at the first line that fails
+fn run_script(path: &str, options: &Options) {
+    let source = read_input(path);
+    let name = if path == "-" { "<stdin>" } else { path };
+    let mut environment = Environment::new();
+    environment.set_order(options.order);
+    let prelude: String = options.definitions.iter().map(|d| format!("{}\n", d)).collect();
+    let result = script::run(&mut environment, &(prelude + &source), |text, value| {
+        let value = match (value, options.sigmas) {
+            (Value::Comparison(c), Some(n)) => Value::Comparison(c.clone().with_sigmas(n)),
+            (value, _) => value.clone(),
+        };
+        println!("{} = {}", text, text_value(&value, options));
+        print_expanded_uncertainty(&value, options);
+    });
+    if let Err(e) = result {
+        let line = e.line as i64 - options.definitions.len() as i64;
+        if line > 0 {
+            match e.span {
+                Some(span) => eprintln!("{}:{}:{}: error: {}", name, line, span.start + 1, e.message),
+                None => eprintln!("{}:{}: error: {}", name, line, e.message),
+            }
+        } else {
+            eprintln!("error: bad definition: {}", e.message);
+        }
+        process::exit(EXIT_FAILURE);
+    }
+}
+
+///Prints the expanded uncertainty of a measurement at the level chosen with --confidence, if any
+fn print_expanded_uncertainty(value: &Value, options: &Options) {
+    let measurement = match value {
+        Value::Measurement(m) => Some(*m),
+        Value::Labeled(m) => Some(m.combined()),
+        _ => None,
+    };
+    if let (Some(level), Some(m)) = (options.confidence, measurement) {
+        println!(
+            "U = {} (k = {}, ν_eff = {}, {}% confidence)",
+            format::number(m.expanded_uncertainty(level), options.precision, options.context.rounding),
+            m.coverage_factor(level),
+            m.dof(),
+            100.0 * level
+        );
+    }
+}
+
+///Declares the definitions given on the command line
+fn environment(options: &Options) -> Environment {
+    let mut environment = Environment::new();
+    environment.set_order(options.order);
+    for (name, expression) in definitions(options) {
+        if let Err(e) = environment.try_assign(name, expression) {
+            fail(expression, &e);
         }
+    }
+    environment
+}
+
+///The definitions given on the command line, as pairs of a name and an expression
+fn definitions(options: &Options) -> Vec<(&str, &str)> {
+    options
+        .definitions
+        .iter()
+        .map(|definition| {
+            let (name, expression) = split_assignment(definition);
+            (name.unwrap_or_default(), expression)
+        })
+        .collect()
+}
+
+///Evaluates a single expression with the chosen mode
+fn evaluate(environment: &Environment, expression: &str, options: &Options, seed: u64) -> Result<Value, Error> {
+    let value = match options.mode {
+        Mode::Linear => environment.try_eval(expression)?,
+        Mode::MonteCarlo => {
+            let result = montecarlo::simulate(&definitions(options), &[expression], options.runs, seed)?;
+            Value::Measurement(Measurement::new(result.means[0], result.sigmas[0]))
+        }
+    };
+    finite(expression, match (value, options.sigmas) {
+        (Value::Comparison(c), Some(n)) => Value::Comparison(c.with_sigmas(n)),
+        (value, _) => value,
+    })
+}
+
+///Fails if the result of the expression is not a finite number, e.g. after a division by zero
+fn finite(expression: &str, value: Value) -> Result<Value, Error> {
+    let (mean, sigma) = match &value {
+        Value::PosNumber(x) | Value::Number(x) => (*x, 0.0),
+        Value::Measurement(m) => (m.mean(), m.sigma()),
+        Value::Labeled(m) => (m.mean(), m.sigma()),
+        Value::Rational(_) | Value::Comparison(_) => return Ok(value),
+    };
+    if mean.is_finite() && sigma.is_finite() {
+        Ok(value)
     } else {
-        println!("Error: too many args.");
-        show_usage();
+        Err(Error::new("the result is not a finite number", Span::new(0, expression.chars().count())))
+    }
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => return,
+        Err(message) => usage_error(&message),
+    };
+    let seed = options.seed.unwrap_or_else(|| {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or_default()
+    });
+
+    let script = match options.expressions.as_slice() {
+        [] if options.script.is_none() && options.csv.is_none() && !io::stdin().is_terminal() => Some("-".to_string()),
+        _ => options.script.clone(),
+    };
+    if let Some(path) = script {
+        if options.mode == Mode::MonteCarlo {
+            usage_error("scripts only support --mode linear");
+        }
+        if options.arithmetic != Arithmetic::Float {
+            usage_error("scripts only support --arithmetic float");
+        }
+        if options.format != Format::Text {
+            usage_error("scripts only support --format text");
+        }
+        run_script(&path, &options);
+        return;
+    }
+    if options.expressions.is_empty() {
+        usage_error("no expression to evaluate");
+    }
+    let environment = environment(&options);
+
+    if let Some(path) = &options.csv {
+        let table = read_input(path);
+        let (name, expression) = split_assignment(&options.expressions[0]);
+        match batch::try_eval_csv(&environment, &table, name.unwrap_or("result"), expression) {
+            Ok(table) => print!("{}", table),
+            Err(e) => fail(expression, &e),
+        }
+        return;
+    }
+
+    if options.format == Format::Latex {
+        for expression in &options.expressions {
+            let result = latex::expression(expression).and_then(|tex| Ok((tex, evaluate(&environment, expression, &options, seed)?)));
+            match result {
+                Ok((tex, value)) => println!("{} = {}", tex, latex::value(&value, &options.style, options.precision, options.context.rounding, options.combined)),
+                Err(e) => fail(expression, &e),
+            }
+        }
+        return;
+    }
+
+    if options.format == Format::Json {
+        let propagation = match options.mode {
+            Mode::Linear => json::Propagation::Taylor(options.order),
+            Mode::MonteCarlo => json::Propagation::MonteCarlo,
+        };
+        let mut failed = false;
+        let objects: Vec<String> = options
+            .expressions
+            .iter()
+            .map(|expression| {
+                let result = evaluate(&environment, expression, &options, seed);
+                failed |= result.is_err();
+                json::to_json(expression, &result, propagation)
+            })
+            .collect();
+        if objects.len() == 1 {
+            println!("{}", objects[0]);
+        } else {
+            println!("[{}]", objects.join(","));
+        }
+        if failed {
+            process::exit(EXIT_FAILURE);
+        }
+        return;
+    }
+
+    if let [expression] = options.expressions.as_slice() {
+        let result = match options.arithmetic {
+            Arithmetic::Float => evaluate(&environment, expression, &options, seed).map(|value| {
+                let text = text_value(&value, &options);
+                (value, text)
+            }),
+            Arithmetic::Decimal => arithmetic::evaluate(&definitions(&options), expression, &options.context).map(|(value, mean)| {
+                let text = format::exact_value(&value, &mean, options.precision, options.context.rounding, options.combined);
+                (value, text)
+            }),
+            Arithmetic::SigFigs => sigfigs::evaluate(&definitions(&options), expression, options.context.rounding)
+                .map(|result| (result.value, format!("{}\n({})", result.text, result.explanation))),
+        };
+        let (value, text) = match result {
+            Ok(result) => result,
+            Err(e) => fail(expression, &e),
+        };
+        println!("{}", text);
+        print_expanded_uncertainty(&value, &options);
+        if let Ok(Some(w)) = environment.try_weighted_mean(expression) {
+            println!(
+                "χ²/ndf = {}/{}, Birge ratio = {}, p = {}",
+                format::number(w.chi_squared, options.precision, options.context.rounding),
+                w.ndf,
+                format::number(w.birge_ratio(), options.precision, options.context.rounding),
+                format::number(w.p_value(), options.precision, options.context.rounding)
+            );
+        }
+        return;
+    }
+
+    //Report the first expression that fails on its own, pointing at the error
+    for expression in &options.expressions {
+        if let Err(e) = environment.try_eval(expression).and_then(|value| finite(expression, value)) {
+            fail(expression, &e);
+        }
+    }
+    let expressions: Vec<&str> = options.expressions.iter().map(|e| e.as_str()).collect();
+    let result = match options.mode {
+        Mode::Linear => environment.try_eval_all(&expressions),
+        Mode::MonteCarlo => {
+            montecarlo::simulate(&definitions(&options), &expressions, options.runs, seed)
+        }
+    };
+    match result {
+        Ok(result) => print!("{}", result.table(options.precision, options.context.rounding)),
+        Err(e) => {
+            eprintln!("error: {}", e.message);
+            process::exit(EXIT_FAILURE);
+        }
     }
 }
@@ -1,150 +1,847 @@
+use crate::arithmetic::{Decimal, Rounding};
+use crate::error::Error;
+use crate::statistics;
 use float_cmp::{ApproxEq, F64Margin};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::ops::{Add, Div, Mul, Sub, Neg};
+use std::str::FromStr;
+
+///Floating-point types in which measurements can be computed, such as `f32` and `f64`.
+///
+///Implemented for every type with the operations of `num_traits::Float`, so a type with
+///more precision than `f64` can be used as well.
+pub trait Float: num_traits::Float + fmt::Debug + fmt::Display {}
+
+impl<T: num_traits::Float + fmt::Debug + fmt::Display> Float for T {}
 
 /**A Measurement 'x' is written as x = (mean +- sigma)
 
 where 'mean' is the mean value
 
-and 'sigma' is the uncertainty(also called error or standard deviation from the mean)*/
+and 'sigma' is the uncertainty(also called error or standard deviation from the mean)
+
+'dof' are the degrees of freedom of the uncertainty: n - 1 for the mean of n readings,
+infinite when the uncertainty is exactly known.
+
+The mean and the uncertainty are of any `Float` type, `f64` unless another one is given:
+`Measurement` is `Measurement<f64>`, while `Measurement<f32>` takes half the memory.
+The degrees of freedom are always an `f64`, as they don't depend on the precision of the values.
+An uncertainty broken down into labeled components is a `LabeledMeasurement`.
+
+With the `serde` feature, a measurement is serialized as a struct with these three fields,
+e.g. `{"mean": 9.81, "sigma": 0.02, "dof": null}` in JSON. Infinite degrees of freedom
+are written as none (`null`), and may be left out.*/
 #[derive(Debug, Clone, Copy)]
-pub struct Measurement {
-    mean: f64,  //mean value
-    sigma: f64, //std deviation, error or uncertainty
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Measurement<T = f64> {
+    pub(crate) mean: T,  //mean value
+    pub(crate) sigma: T, //std deviation, error or uncertainty
+    #[cfg_attr(feature = "serde", serde(default = "exact", with = "infinite_as_none"))]
+    pub(crate) dof: f64, //degrees of freedom of the uncertainty
+}
+
+///Degrees of freedom of an exactly known uncertainty
+#[cfg(feature = "serde")]
+fn exact() -> f64 {
+    f64::INFINITY
+}
+
+///Serializes infinite degrees of freedom as none, since formats such as JSON have no infinity
+#[cfg(feature = "serde")]
+mod infinite_as_none {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(dof: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if dof.is_finite() {
+            serializer.serialize_some(dof)
+        } else {
+            serializer.serialize_none()
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        Ok(Option::<f64>::deserialize(deserializer)?.unwrap_or(f64::INFINITY))
+    }
+}
+
+impl<T: Float> Measurement<T> {
+    ///Creates the measurement `mean ± sigma`, without validating `sigma`: see `try_new`
+    pub fn new(mean: T, sigma: T) -> Self {
+        debug_assert!(sigma >= T::zero() || sigma.is_nan(), "the uncertainty must be a non-negative number, got {}", sigma);
+        Measurement { mean, sigma, dof: f64::INFINITY }
+    }
+
+    ///Creates the measurement `mean ± sigma`, or fails if `sigma` is negative or NaN
+    pub fn try_new(mean: T, sigma: T) -> Result<Self, Error> {
+        if sigma.is_nan() || sigma < T::zero() {
+            return Err(Error::unlocated(format!("the uncertainty must be a non-negative number, got {}", sigma)));
+        }
+        Ok(Measurement::new(mean, sigma))
+    }
+
+    ///An exactly known value, without uncertainty
+    pub fn exact(value: T) -> Self {
+        Measurement::new(value, T::zero())
+    }
+
+    ///Creates a measurement from its relative uncertainty, e.g.
+    ///`Measurement::from_relative(200.0, 0.01)` is `200 ± 2`
+    pub fn from_relative(mean: T, relative: T) -> Self {
+        Measurement::new(mean, (relative * mean).abs())
+    }
+
+    ///The mean value
+    pub fn mean(&self) -> T {
+        self.mean
+    }
+
+    ///The (combined) standard uncertainty
+    pub fn sigma(&self) -> T {
+        self.sigma
+    }
+
+    ///The square of the uncertainty
+    pub fn variance(&self) -> T {
+        self.sigma * self.sigma
+    }
+
+    ///The uncertainty relative to the magnitude of the mean, e.g. 0.01 for `200 ± 2`.
+    ///
+    ///Infinite if the mean is zero, unless the measurement is exact.
+    pub fn relative(&self) -> T {
+        if self.sigma == T::zero() {
+            T::zero()
+        } else {
+            self.sigma / self.mean.abs()
+        }
+    }
+
+    ///Type B evaluation of a rectangular (uniform) distribution of half-width `a`,
+    ///e.g. a tolerance of ±a on a data sheet: the standard uncertainty is a/√3.
+    ///
+    ///Panics if `a` is negative or NaN, see `try_rectangular`.
+    pub fn rectangular(mean: T, a: T) -> Self {
+        type_b(Measurement::try_rectangular(mean, a))
+    }
+
+    ///Same as `rectangular`, but fails if `a` is negative or NaN
+    pub fn try_rectangular(mean: T, a: T) -> Result<Self, Error> {
+        Ok(Measurement::new(mean, half_width(a)? / constant::<T>(3.0).sqrt()))
+    }
+
+    ///Type B evaluation of a triangular distribution of half-width `a`: the standard uncertainty is a/√6.
+    ///
+    ///Panics if `a` is negative or NaN, see `try_triangular`.
+    pub fn triangular(mean: T, a: T) -> Self {
+        type_b(Measurement::try_triangular(mean, a))
+    }
+
+    ///Same as `triangular`, but fails if `a` is negative or NaN
+    pub fn try_triangular(mean: T, a: T) -> Result<Self, Error> {
+        Ok(Measurement::new(mean, half_width(a)? / constant::<T>(6.0).sqrt()))
+    }
+
+    ///Type B evaluation of a U-shaped (arcsine) distribution of half-width `a`,
+    ///such as a sinusoidally varying quantity: the standard uncertainty is a/√2.
+    ///
+    ///Panics if `a` is negative or NaN, see `try_u_shaped`.
+    pub fn u_shaped(mean: T, a: T) -> Self {
+        type_b(Measurement::try_u_shaped(mean, a))
+    }
+
+    ///Same as `u_shaped`, but fails if `a` is negative or NaN
+    pub fn try_u_shaped(mean: T, a: T) -> Result<Self, Error> {
+        Ok(Measurement::new(mean, half_width(a)? / constant::<T>(2.0).sqrt()))
+    }
+
+    ///The same measurement, with the given degrees of freedom
+    pub fn with_dof(self, dof: f64) -> Self {
+        Measurement { dof, ..self }
+    }
+
+    ///Degrees of freedom of the uncertainty, infinite if it is exactly known
+    pub fn dof(&self) -> f64 {
+        self.dof
+    }
+
+    ///Coverage factor k of an interval with the given confidence level (e.g. 0.95),
+    ///from Student's t distribution with the degrees of freedom of the measurement
+    pub fn coverage_factor(&self, confidence: f64) -> f64 {
+        statistics::coverage_factor(confidence, self.dof)
+    }
+
+    ///Expanded uncertainty U = k·u, the half-width of an interval around the mean
+    ///with the given confidence level (e.g. 0.95)
+    pub fn expanded_uncertainty(&self, confidence: f64) -> T {
+        constant::<T>(self.coverage_factor(confidence)) * self.sigma
+    }
+
+    ///Rounds the uncertainty to `sig_figs` significant figures, and the mean to the same
+    ///decimal place, returning both as text. The digits shown by `Display` are rounded in
+    ///decimal, so without floating-point artifacts: `9.8123 ± 0.0234` is `("9.81", "0.02")`
+    ///with one significant figure. Without a finite, non-zero uncertainty, both are written
+    ///with all their digits.
+    pub fn round_to_uncertainty(&self, sig_figs: usize, rounding: Rounding) -> (String, String) {
+        let decimal = |x: T| x.to_string().parse::<Decimal>().ok();
+        match (decimal(self.mean), decimal(self.sigma)) {
+            (Some(mean), Some(sigma)) if !sigma.is_zero() => {
+                let place = sigma.sig_figs_place(sig_figs, rounding);
+                (mean.to_places(place, rounding), sigma.to_places(place, rounding))
+            }
+            _ => (self.mean.to_string(), self.sigma.to_string()),
+        }
+    }
+
+    ///Tests whether two independent measurements (or a measurement and a reference value
+    ///without uncertainty) agree, from the z-score of their difference
+    pub fn compare(&self, other: &Self) -> Comparison {
+        let difference = *self - *other;
+        Comparison::new(to_f64(difference.mean), to_f64(difference.sigma), difference.dof)
+    }
+
+    ///Propagates the uncertainties of two independent measurements to a function
+    ///of both, given its partial derivatives with respect to each of them.
+    ///
+    ///The degrees of freedom are combined with the Welch–Satterthwaite formula.
+    fn propagate(&self, d_self: T, other: &Self, d_other: T, mean: T) -> Self {
+        let sigma = quadrature(d_self * self.sigma, d_other * other.sigma).sqrt();
+        let contributions = [(to_f64(d_self * self.sigma), self.dof), (to_f64(d_other * other.sigma), other.dof)];
+        let dof = statistics::welch_satterthwaite(to_f64(sigma), &contributions);
+        Measurement::new(mean, sigma).with_dof(dof)
+    }
+
+    ///Multiplies the uncertainty by |k|
+    fn scale(&self, k: T, mean: T) -> Self {
+        Self {
+            mean,
+            sigma: (self.sigma * k).abs(),
+            dof: self.dof,
+        }
+    }
+
+    ///Raises the measurement to a constant (exact) power `n`
+    pub fn powf(self, n: T) -> Self {
+        self.scale(n * self.mean.powf(n - T::one()), self.mean.powf(n))
+    }
+
+    ///Raises the measurement to a power which is itself a measurement,
+    ///i.e. computes x^y where both 'x' and 'y' have uncertainties
+    pub fn pow(self, other: Self) -> Self {
+        let new_mean = self.mean.powf(other.mean);
+        let d_base = other.mean * self.mean.powf(other.mean - T::one());
+        let d_exponent = self.mean.ln() * new_mean;
+        self.propagate(d_base, &other, d_exponent, new_mean)
+    }
 }
 
 impl Measurement {
-    pub fn new(mean: f64, sigma: f64) -> Measurement {
-        Measurement { mean, sigma }
+    ///Type A evaluation: the mean of repeated readings, with the standard error
+    ///of the mean as its uncertainty and n - 1 degrees of freedom.
+    ///
+    ///Fails with fewer than two readings, whose spread is unknown.
+    pub fn from_samples(samples: &[f64]) -> Result<Measurement, Error> {
+        if samples.len() < 2 {
+            return Err(Error::unlocated(format!("at least two samples are needed, got {}", samples.len())));
+        }
+        let m = Measurement::new(statistics::mean(samples), statistics::sem(samples));
+        Ok(m.with_dof((samples.len() - 1) as f64))
+    }
+
+    ///Combines independent measurements of the same quantity, weighting each one
+    ///by the inverse of its variance. The chi-square of the measurements around
+    ///the weighted mean tells whether they are consistent with each other.
+    ///
+    ///Fails if there are no measurements, or if one of them has no uncertainty.
+    pub fn weighted_mean(measurements: &[Measurement]) -> Result<WeightedMean, Error> {
+        if measurements.is_empty() {
+            return Err(Error::unlocated("the weighted mean of an empty list of measurements is undefined"));
+        }
+        if let Some(m) = measurements.iter().find(|m| m.sigma.is_nan() || m.sigma <= 0.0) {
+            return Err(Error::unlocated(format!("cannot weight a measurement without uncertainty: {}", m)));
+        }
+        let total: f64 = measurements.iter().map(|m| 1.0 / (m.sigma * m.sigma)).sum();
+        let mean = measurements
+            .iter()
+            .map(|&m| m * (1.0 / (m.sigma * m.sigma * total)))
+            .reduce(|x, y| x + y)
+            .unwrap();
+        let chi_squared = measurements.iter().map(|m| ((m.mean - mean.mean) / m.sigma).powi(2)).sum();
+        Ok(WeightedMean { mean, chi_squared, ndf: measurements.len() - 1 })
     }
 }
 
-impl Neg for Measurement {
+impl<T: Float> Neg for Measurement<T> {
     type Output = Self;
 
     fn neg(self) -> Self {
         Self {
             mean: -self.mean,
-            sigma: self.sigma,
+            ..self
         }
     }
 }
 
-impl Add for Measurement {
+impl<T: Float> Add for Measurement<T> {
     type Output = Self;
 
     fn add(self, other: Self) -> Self {
-        Self {
-            mean: self.mean + other.mean,
-            sigma: quadrature(self.sigma, other.sigma).sqrt(),
-        }
+        self.propagate(T::one(), &other, T::one(), self.mean + other.mean)
     }
 }
 
-impl Add<f64> for Measurement {
+impl<T: Float> Add<T> for Measurement<T> {
     type Output = Self;
 
-    fn add(self, other: f64) -> Self {
+    fn add(self, other: T) -> Self {
         Self {
             mean: self.mean + other,
-            sigma: self.sigma,
+            ..self
         }
     }
 }
 
-impl Sub for Measurement {
+impl<T: Float> Sub for Measurement<T> {
     type Output = Self;
 
     fn sub(self, other: Self) -> Self {
-        Self {
-            mean: self.mean - other.mean,
-            sigma: quadrature(self.sigma, other.sigma).sqrt(),
-        }
+        self.propagate(T::one(), &other, T::one(), self.mean - other.mean)
     }
 }
 
-impl Sub<f64> for Measurement {
+impl<T: Float> Sub<T> for Measurement<T> {
     type Output = Self;
 
-    fn sub(self, other: f64) -> Self {
+    fn sub(self, other: T) -> Self {
         Self {
             mean: self.mean - other,
-            sigma: self.sigma,
+            ..self
         }
     }
 }
 
-impl Mul for Measurement {
+impl<T: Float> Mul for Measurement<T> {
     type Output = Self;
 
     fn mul(self, other: Self) -> Self {
-        let new_mean = self.mean * other.mean;
-        Self {
-            mean: new_mean,
-            sigma: quadrature(self.sigma / self.mean, other.sigma / other.mean).sqrt() * new_mean,
-        }
+        //∂(xy)/∂x = y and ∂(xy)/∂y = x
+        self.propagate(other.mean, &other, self.mean, self.mean * other.mean)
     }
 }
 
-impl Mul<f64> for Measurement {
+impl<T: Float> Mul<T> for Measurement<T> {
     type Output = Self;
 
-    fn mul(self, other: f64) -> Self {
-        Self {
-            mean: self.mean * other,
-            sigma: self.sigma * other,
-        }
+    fn mul(self, other: T) -> Self {
+        self.scale(other, self.mean * other)
     }
 }
 
-impl Div for Measurement {
+impl<T: Float> Div for Measurement<T> {
     type Output = Self;
 
     fn div(self, other: Self) -> Self {
-        let new_mean = self.mean / other.mean;
-        Self {
-            mean: new_mean,
-            sigma: quadrature(self.sigma / self.mean, other.sigma / other.mean).sqrt() * new_mean,
-        }
+        //∂(x/y)/∂x = 1/y and ∂(x/y)/∂y = -x/y²
+        let d_self = T::one() / other.mean;
+        let d_other = -self.mean / (other.mean * other.mean);
+        self.propagate(d_self, &other, d_other, self.mean / other.mean)
     }
 }
 
-impl Div<f64> for Measurement {
+impl<T: Float> Div<T> for Measurement<T> {
     type Output = Self;
 
-    fn div(self, other: f64) -> Self {
+    fn div(self, other: T) -> Self {
         Self {
             mean: self.mean / other,
-            sigma: self.sigma / other,
+            sigma: (self.sigma / other).abs(),
+            dof: self.dof,
+        }
+    }
+}
+
+/**A measurement whose uncertainty is broken down into labeled components, such as
+x = 12.3 ± 0.4 (stat) ± 0.2 (syst). Its uncertainty is their combination in quadrature.
+
+Each component is propagated separately: the components of independent measurements
+with the same label add in quadrature.
+
+Unlike `Measurement`, it owns its labels, so it is not `Copy`.
+
+With the `serde` feature, it is serialized as a measurement with its components,
+e.g. `{"mean": 12.3, "sigma": 0.447, "dof": null, "components": [["stat", 0.4], ["syst", 0.2]]}` in JSON.*/
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LabeledMeasurement<T = f64> {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub(crate) measurement: Measurement<T>, //combined uncertainty
+    pub(crate) components: Vec<(String, T)>, //(label, sigma) of each component. Empty if unlabeled
+}
+
+impl<T: Float> LabeledMeasurement<T> {
+    ///Creates a measurement whose uncertainty has several labeled components,
+    ///e.g. `LabeledMeasurement::with_components(12.3, &[("stat", 0.4), ("syst", 0.2)])`
+    ///
+    ///An empty label stands for the unlabeled part of the uncertainty.
+    pub fn with_components(mean: T, components: &[(&str, T)]) -> Self {
+        let mut result = LabeledMeasurement::from(Measurement::exact(mean));
+        for &(label, sigma) in components {
+            result = result.add_component(label, sigma);
+        }
+        result
+    }
+
+    ///The mean value
+    pub fn mean(&self) -> T {
+        self.measurement.mean
+    }
+
+    ///The combined standard uncertainty
+    pub fn sigma(&self) -> T {
+        self.measurement.sigma
+    }
+
+    ///Degrees of freedom of the combined uncertainty
+    pub fn dof(&self) -> f64 {
+        self.measurement.dof
+    }
+
+    ///The labeled components of the uncertainty, in the order they were introduced.
+    ///
+    ///Empty if the uncertainty is not broken down into components.
+    pub fn components(&self) -> &[(String, T)] {
+        &self.components
+    }
+
+    ///The component of the uncertainty with the given label, if any
+    pub fn component(&self, label: &str) -> Option<T> {
+        self.components.iter().find(|(l, _)| l == label).map(|&(_, sigma)| sigma)
+    }
+
+    ///The same measurement, with all the components combined in quadrature
+    pub fn combined(&self) -> Measurement<T> {
+        self.measurement
+    }
+
+    ///Adds another independent source of uncertainty,
+    ///e.g. a systematic error on top of a statistical one
+    pub fn add_component(self, label: &str, sigma: T) -> Self {
+        let mut components = self.labeled();
+        match components.iter_mut().find(|(l, _)| l == label) {
+            Some(component) => component.1 = quadrature(component.1, sigma).sqrt(),
+            None => components.push((label.into(), sigma)),
+        }
+        LabeledMeasurement::from_components(self.mean(), components)
+    }
+
+    ///The uncertainty broken down by label, where the unlabeled part has an empty label.
+    ///Empty for an exact value.
+    fn labeled(&self) -> Vec<(String, T)> {
+        match self.sigma() {
+            _ if !self.components.is_empty() => self.components.clone(),
+            sigma if sigma == T::zero() => Vec::new(),
+            sigma => vec![(String::new(), sigma)],
+        }
+    }
+
+    fn from_components(mean: T, components: Vec<(String, T)>) -> Self {
+        let sigma = components.iter().fold(T::zero(), |sum, &(_, s)| sum + s * s).sqrt();
+        let measurement = Measurement::new(mean, sigma);
+        if components.iter().all(|(label, _)| label.is_empty()) {
+            LabeledMeasurement::from(measurement)
+        } else {
+            LabeledMeasurement { measurement, components }
+        }
+    }
+
+    ///Propagates each component of two independent measurements to a function of both,
+    ///given its partial derivatives with respect to each of them
+    fn propagate(&self, d_self: T, other: &Self, d_other: T, mean: T) -> Self {
+        let combined = self.measurement.propagate(d_self, &other.measurement, d_other, mean);
+        if self.components.is_empty() && other.components.is_empty() {
+            return LabeledMeasurement::from(combined);
+        }
+        let mut components: Vec<(String, T)> = Vec::new();
+        for (d, (label, sigma)) in self.labeled().into_iter().map(|c| (d_self, c)).chain(other.labeled().into_iter().map(|c| (d_other, c))) {
+            match components.iter_mut().find(|(l, _)| *l == label) {
+                Some(component) => component.1 = quadrature(component.1, d * sigma).sqrt(),
+                None => components.push((label, (d * sigma).abs())),
+            }
         }
+        let mut result = LabeledMeasurement::from_components(mean, components);
+        result.measurement.dof = combined.dof;
+        result
+    }
+
+    ///Multiplies the uncertainty, and each of its components, by |k|
+    fn scale(&self, k: T, mean: T) -> Self {
+        LabeledMeasurement {
+            measurement: self.measurement.scale(k, mean),
+            components: self.components.iter().map(|(l, s)| (l.clone(), (*s * k).abs())).collect(),
+        }
+    }
+
+    ///Raises the measurement to a constant (exact) power `n`
+    pub fn powf(self, n: T) -> Self {
+        let mean = self.mean();
+        self.scale(n * mean.powf(n - T::one()), mean.powf(n))
+    }
+
+    ///Raises the measurement to a power which is itself a measurement,
+    ///i.e. computes x^y where both 'x' and 'y' have uncertainties
+    pub fn pow(self, other: Self) -> Self {
+        let (x, y) = (self.mean(), other.mean());
+        let new_mean = x.powf(y);
+        self.propagate(y * x.powf(y - T::one()), &other, x.ln() * new_mean, new_mean)
+    }
+}
+
+///A measurement whose uncertainty is not broken down
+impl<T: Float> From<Measurement<T>> for LabeledMeasurement<T> {
+    fn from(measurement: Measurement<T>) -> Self {
+        LabeledMeasurement { measurement, components: Vec::new() }
+    }
+}
+
+impl<T: Float> Neg for LabeledMeasurement<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        LabeledMeasurement { measurement: -self.measurement, ..self }
+    }
+}
+
+impl<T: Float> Add for LabeledMeasurement<T> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let mean = self.mean() + other.mean();
+        self.propagate(T::one(), &other, T::one(), mean)
+    }
+}
+
+impl<T: Float> Add<T> for LabeledMeasurement<T> {
+    type Output = Self;
+
+    fn add(self, other: T) -> Self {
+        LabeledMeasurement { measurement: self.measurement + other, ..self }
+    }
+}
+
+impl<T: Float> Sub for LabeledMeasurement<T> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        let mean = self.mean() - other.mean();
+        self.propagate(T::one(), &other, T::one(), mean)
+    }
+}
+
+impl<T: Float> Sub<T> for LabeledMeasurement<T> {
+    type Output = Self;
+
+    fn sub(self, other: T) -> Self {
+        LabeledMeasurement { measurement: self.measurement - other, ..self }
     }
 }
 
-impl fmt::Display for Measurement {
+impl<T: Float> Mul for LabeledMeasurement<T> {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let (x, y) = (self.mean(), other.mean());
+        self.propagate(y, &other, x, x * y)
+    }
+}
+
+impl<T: Float> Mul<T> for LabeledMeasurement<T> {
+    type Output = Self;
+
+    fn mul(self, other: T) -> Self {
+        self.scale(other, self.mean() * other)
+    }
+}
+
+impl<T: Float> Div for LabeledMeasurement<T> {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        let (x, y) = (self.mean(), other.mean());
+        self.propagate(T::one() / y, &other, -x / (y * y), x / y)
+    }
+}
+
+impl<T: Float> Div<T> for LabeledMeasurement<T> {
+    type Output = Self;
+
+    fn div(self, other: T) -> Self {
+        LabeledMeasurement {
+            measurement: self.measurement / other,
+            components: self.components.iter().map(|(l, s)| (l.clone(), (*s / other).abs())).collect(),
+        }
+    }
+}
+
+///Result of `Measurement::weighted_mean`
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightedMean {
+    pub mean: Measurement,   //inverse-variance weighted mean
+    pub chi_squared: f64,    //chi-square of the measurements around the mean
+    pub ndf: usize,          //number of degrees of freedom of the chi-square, n - 1
+}
+
+impl WeightedMean {
+    ///Birge ratio √(χ²/ndf). Values much larger than one mean that the uncertainties
+    ///were underestimated, or that the measurements are inconsistent.
+    ///
+    ///Undefined (NaN) for a single measurement.
+    pub fn birge_ratio(&self) -> f64 {
+        (self.chi_squared / self.ndf as f64).sqrt()
+    }
+
+    ///Probability of a chi-square at least as large, if the measurements are consistent.
+    ///
+    ///Undefined (NaN) for a single measurement.
+    pub fn p_value(&self) -> f64 {
+        statistics::chi_squared_p_value(self.chi_squared, self.ndf)
+    }
+
+    ///The weighted mean, with its uncertainty scaled up by the Birge ratio when it is larger than one
+    pub fn scaled(&self) -> Measurement {
+        let ratio = self.birge_ratio();
+        if ratio > 1.0 {
+            self.mean.scale(ratio, self.mean.mean)
+        } else {
+            self.mean
+        }
+    }
+}
+
+impl fmt::Display for WeightedMean {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.mean, f)?;
+        write!(f, " (χ²/ndf = {}/{}, Birge ratio = {})", self.chi_squared, self.ndf, self.birge_ratio())
+    }
+}
+
+///Result of comparing two measurements, e.g. `compare(9.79 ± 0.02, 9.80665)`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Comparison {
+    pub difference: f64, //difference between the mean values
+    pub sigma: f64,      //combined uncertainty of the difference
+    pub z_score: f64,    //difference over its uncertainty
+    pub p_value: f64,    //two-sided p-value of the z-score
+    pub sigmas: f64,     //the measurements agree if |z| is at most this number of sigmas
+}
+
+impl Comparison {
+    ///Compares a difference with its uncertainty, which has `dof` degrees of freedom.
+    ///The p-value comes from Student's t distribution (normal if `dof` is infinite).
+    ///
+    ///Agreement is judged at two sigmas, see `with_sigmas`.
+    ///
+    ///Without uncertainty the comparison is exact: equal values have a z-score of 0 and
+    ///a p-value of 1, and different ones an infinite z-score and a p-value of 0.
+    pub fn new(difference: f64, sigma: f64, dof: f64) -> Comparison {
+        let (z_score, p_value) = match difference / sigma {
+            _ if sigma == 0.0 && difference == 0.0 => (0.0, 1.0),
+            z if z.is_infinite() => (z, 0.0),
+            z => (z, statistics::two_sided_p_value(z, dof)),
+        };
+        Comparison { difference, sigma, z_score, p_value, sigmas: 2.0 }
+    }
+
+    ///The same comparison, judging agreement at the given number of sigmas
+    pub fn with_sigmas(self, sigmas: f64) -> Comparison {
+        Comparison { sigmas, ..self }
+    }
+
+    ///True if the difference is within the chosen number of sigmas
+    pub fn agrees(&self) -> bool {
+        self.z_score.abs() <= self.sigmas
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.agrees() { "agree within" } else { "disagree at" };
+        write!(f, "z = {}, p = {} ({} {}σ)", self.z_score, self.p_value, verdict, self.sigmas)
+    }
+}
+
+impl<T: Float> fmt::Display for Measurement<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} ± {}", self.mean, self.sigma)
     }
 }
 
-impl PartialEq for Measurement {
+///Shows each component of the uncertainty separately, e.g. `12.3 ± 0.4 (stat) ± 0.2 (syst)`
+///
+///The alternate form (`{:#}`) combines them in quadrature, e.g. `12.3 ± 0.447`
+impl<T: Float> fmt::Display for LabeledMeasurement<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() || self.components.is_empty() {
+            return fmt::Display::fmt(&self.measurement, f);
+        }
+        write!(f, "{}", self.mean())?;
+        for (label, sigma) in &self.components {
+            if label.is_empty() {
+                write!(f, " ± {}", sigma)?;
+            } else {
+                write!(f, " ± {} ({})", sigma, label)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+///Reads a measurement written as `mean ± sigma`, `mean +/- sigma` or `mean +- sigma`, e.g. `1.2 ± 0.1`.
+///Labeled components, e.g. `12.3 ± 0.4 (stat) ± 0.2 (syst)`, are combined in quadrature:
+///see `LabeledMeasurement` to keep them.
+///
+///The concise form `mean(sigma)` gives the uncertainty in units of the last digit of
+///the mean, e.g. `1.23(4)` is `1.23 ± 0.04` and `1.5e3(2)` is `1500 ± 200`, unless it has a
+///period: `12.3(1.2)` is `12.3 ± 1.2`.
+///A number alone is an exact measurement.
+impl<T: Float + FromStr> FromStr for Measurement<T> {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Ok(text.parse::<LabeledMeasurement<T>>()?.combined())
+    }
+}
+
+///Reads a measurement in any of the forms accepted by `Measurement`, keeping its
+///labeled components, e.g. `12.3 ± 0.4 (stat) ± 0.2 (syst)`
+impl<T: Float + FromStr> FromStr for LabeledMeasurement<T> {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let number = |text: &str| -> Result<T, Error> {
+            text.trim().parse().map_err(|_| Error::unlocated(format!("invalid number: \"{}\"", text.trim())))
+        };
+        let uncertainty = |text: &str| -> Result<T, Error> {
+            match number(text)? {
+                sigma if sigma >= T::zero() => Ok(sigma),
+                sigma => Err(Error::unlocated(format!("the uncertainty must be a non-negative number, got {}", sigma))),
+            }
+        };
+        //Splits 'x (label)' into 'x' and the label
+        fn labeled(text: &str) -> Option<(&str, &str)> {
+            let (value, label) = text.trim().strip_suffix(')')?.split_once('(')?;
+            Some((value, label.trim()))
+        }
+
+        let text = text.trim().replace("+/-", "±").replace("+-", "±");
+        let mut parts = text.split('±');
+        let mean = parts.next().unwrap_or_default();
+        let uncertainties: Vec<&str> = parts.collect();
+        if uncertainties.is_empty() {
+            let measurement = match labeled(mean) {
+                //Concise form, e.g. '1.23(4)'
+                Some((mean, digits)) if digits.contains('.') => Measurement::new(number(mean)?, uncertainty(digits)?),
+                Some((mean, digits)) => {
+                    //The digits are in units of the last digit written, e.g. the hundreds of '1.5e3(2)'
+                    let (mantissa, exponent) = mean.trim().split_once(['e', 'E']).unwrap_or((mean.trim(), "0"));
+                    let exponent: i32 = exponent.parse().map_err(|_| Error::unlocated(format!("invalid number: \"{}\"", mean.trim())))?;
+                    let decimals = mantissa.split_once('.').map(|(_, d)| d.len()).unwrap_or(0) as i32;
+                    let digits: u64 = digits.parse().map_err(|_| Error::unlocated(format!("invalid uncertainty: \"{}\"", digits)))?;
+                    Measurement::new(number(mean)?, constant::<T>(digits as f64) / constant::<T>(10.0).powi(decimals - exponent))
+                },
+                None => Measurement::exact(number(mean)?),
+            };
+            return Ok(LabeledMeasurement::from(measurement));
+        }
+        let mut components = Vec::new();
+        for part in uncertainties {
+            match labeled(part) {
+                Some((sigma, label)) if !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '_') => {
+                    components.push((label, uncertainty(sigma)?))
+                },
+                Some((_, label)) => return Err(Error::unlocated(format!("bad label: \"{}\"", label))),
+                None => components.push(("", uncertainty(part)?)),
+            }
+        }
+        match components.as_slice() {
+            [("", sigma)] => Ok(LabeledMeasurement::from(Measurement::new(number(mean)?, *sigma))),
+            _ => Ok(LabeledMeasurement::with_components(number(mean)?, &components)),
+        }
+    }
+}
+
+impl<T: Float> PartialEq for Measurement<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.mean == other.mean && self.sigma == other.sigma && self.dof == other.dof
+    }
+}
+
+impl<T: Float> PartialEq for LabeledMeasurement<T> {
     fn eq(&self, other: &Self) -> bool {
-        self.mean == other.mean && self.sigma == other.sigma
+        self.measurement == other.measurement && self.components == other.components
+    }
+}
+
+///Compares the means and uncertainties within the margin of the float type,
+///e.g. `F64Margin` for `f64` and `F32Margin` for `f32`
+impl<T: Float + ApproxEq> ApproxEq for Measurement<T> {
+    type Margin = T::Margin;
+
+    fn approx_eq<M: Into<Self::Margin>>(self, other: Self, margin: M) -> bool {
+        let margin = margin.into();
+        self.mean.approx_eq(other.mean, margin)
+            && self.sigma.approx_eq(other.sigma, margin)
+            && (self.dof == other.dof || self.dof.approx_eq(other.dof, F64Margin::default()))
     }
 }
 
-impl ApproxEq for Measurement {
-    type Margin = F64Margin;
+///Compares the measurements and their components within the margin of the float type
+impl<T: Float + ApproxEq> ApproxEq for LabeledMeasurement<T> {
+    type Margin = T::Margin;
 
-    fn approx_eq<T: Into<Self::Margin>>(self, other: Self, margin: T) -> bool {
+    fn approx_eq<M: Into<Self::Margin>>(self, other: Self, margin: M) -> bool {
         let margin = margin.into();
-        self.mean.approx_eq(other.mean, margin) && self.sigma.approx_eq(other.sigma, margin)
+        self.measurement.approx_eq(other.measurement, margin)
+            && self.components.len() == other.components.len()
+            && self
+                .components
+                .iter()
+                .zip(other.components.iter())
+                .all(|((l1, s1), (l2, s2))| l1 == l2 && s1.approx_eq(*s2, margin))
+    }
+}
+
+fn half_width<T: Float>(a: T) -> Result<T, Error> {
+    if a.is_nan() || a < T::zero() {
+        return Err(Error::unlocated(format!("the half-width of a distribution must be a non-negative number, got {}", a)));
+    }
+    Ok(a)
+}
+
+///The result of a Type B evaluation, panicking on an invalid half-width
+fn type_b<T: Float>(result: Result<Measurement<T>, Error>) -> Measurement<T> {
+    match result {
+        Ok(m) => m,
+        Err(e) => panic!("{}", e),
     }
 }
 
-fn quadrature(x: f64, y: f64) -> f64 {
+fn quadrature<T: Float>(x: T, y: T) -> T {
     x * x + y * y
 }
 
+///A constant of the float type, e.g. `constant::<f32>(3.0)`
+fn constant<T: Float>(x: f64) -> T {
+    T::from(x).unwrap_or_else(T::nan)
+}
+
+///The value as an `f64`, for the statistical functions
+fn to_f64<T: Float>(x: T) -> f64 {
+    x.to_f64().unwrap_or(f64::NAN)
+}
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -155,8 +852,8 @@ mod tests {
 
         let added = Measurement::new(3.0, 0.0002f64.sqrt());
         let subtracted = Measurement::new(-1.0, 0.0002f64.sqrt());
-        let multiplied = Measurement::new(2.0, 2.0 * (quadrature(0.01 / 1.0, 0.01 / 2.0)).sqrt());
-        let divided = Measurement::new(0.5, 0.5 * (quadrature(0.01 / 1.0, 0.01 / 2.0)).sqrt());
+        let multiplied = Measurement::new(2.0, 2.0 * (quadrature(0.01f64 / 1.0, 0.01 / 2.0)).sqrt());
+        let divided = Measurement::new(0.5, 0.5 * (quadrature(0.01f64 / 1.0, 0.01 / 2.0)).sqrt());
 
         assert!(added.approx_eq(x + y, F64Margin::default()));
         assert!(subtracted.approx_eq(x - y, F64Margin::default()));
@@ -164,6 +861,69 @@ mod tests {
         assert!(divided.approx_eq(x / y, F64Margin::default()));
     }
     #[test]
+    fn powers() {
+        let x = Measurement::new(2.0, 0.01);
+        let y = Measurement::new(3.0, 0.02);
+
+        let squared = Measurement::new(4.0, 2.0 * 2.0 * 0.01);
+        let root = Measurement::new(2.0f64.sqrt(), 0.5 * 0.01 / 2.0f64.sqrt());
+        let general = Measurement::new(
+            8.0,
+            quadrature(3.0 * 4.0 * 0.01, 2.0f64.ln() * 8.0 * 0.02).sqrt(),
+        );
+
+        assert!(squared.approx_eq(x.powf(2.0), F64Margin::default()));
+        assert!(root.approx_eq(x.powf(0.5), F64Margin::default()));
+        assert!(general.approx_eq(x.pow(y), F64Margin::default()));
+    }
+    #[test]
+    fn zero_mean_operations() {
+        let zero = Measurement::new(0.0, 0.1);
+        let x = Measurement::new(2.0, 0.1);
+
+        assert!(Measurement::new(0.0, 0.2).approx_eq(zero * x, F64Margin::default()));
+        assert!(Measurement::new(0.0, 0.2).approx_eq(x * zero, F64Margin::default()));
+        assert!(Measurement::new(0.0, 0.05).approx_eq(zero / x, F64Margin::default()));
+        //Both partial derivatives vanish, so the first order uncertainty is zero
+        assert!(Measurement::new(0.0, 0.0).approx_eq(zero * zero, F64Margin::default()));
+        //A zero mean with no uncertainty is an exact zero
+        assert!(Measurement::new(0.0, 0.0).approx_eq(Measurement::new(0.0, 0.0) * x, F64Margin::default()));
+        //Dividing an exact constant is the same as dividing its value
+        let inverse = Measurement::new(1.0, 0.0) / x;
+        assert!(Measurement::new(0.5, 0.1 / 4.0).approx_eq(inverse, F64Margin::default()));
+        assert!(Measurement::new(0.0, 0.0).approx_eq(Measurement::new(0.0, 0.0) / x, F64Margin::default()));
+    }
+    #[test]
+    fn negative_mean_operations() {
+        let x = Measurement::new(-2.0, 0.1);
+        let y = Measurement::new(-4.0, 0.2);
+
+        let multiplied = Measurement::new(8.0, quadrature(4.0f64 * 0.1, 2.0 * 0.2).sqrt());
+        let divided = Measurement::new(0.5, quadrature(0.1f64 / 4.0, 2.0 * 0.2 / 16.0).sqrt());
+
+        assert!(multiplied.approx_eq(x * y, F64Margin::default()));
+        assert!(divided.approx_eq(x / y, F64Margin::default()));
+        assert!(Measurement::new(4.0, 0.2).approx_eq(x * -2.0, F64Margin::default()));
+        assert!(Measurement::new(1.0, 0.05).approx_eq(x / -2.0, F64Margin::default()));
+    }
+    #[test]
+    fn mixed_sign_products() {
+        let x = Measurement::new(-2.0, 0.1);
+        let y = Measurement::new(3.0, 0.2);
+
+        let multiplied = Measurement::new(-6.0, quadrature(3.0f64 * 0.1, 2.0 * 0.2).sqrt());
+        let divided = Measurement::new(-2.0 / 3.0, quadrature(0.1f64 / 3.0, 2.0 * 0.2 / 9.0).sqrt());
+
+        assert!(multiplied.approx_eq(x * y, F64Margin::default()));
+        assert!(multiplied.approx_eq(y * x, F64Margin::default()));
+        assert!(divided.approx_eq(x / y, F64Margin::default()));
+        //Uncertainties are never negative, regardless of the signs involved
+        for m in &[x * y, y * x, x / y, y / x, x * -1.0, x / -1.0] {
+            assert!(m.sigma >= 0.0);
+        }
+    }
+    #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn approximate_equality() {
         /* Tests the approximate equality due to floating point errors */
         let x = Measurement::new(1.0, 0.01);
@@ -177,4 +937,190 @@ mod tests {
         //x and x_prime should be equal
         assert!(x.approx_eq(x_prime, F64Margin::default()));
     }
+    #[test]
+    fn samples() {
+        let x = Measurement::from_samples(&[9.81, 9.79, 9.83, 9.80]).unwrap();
+        assert!(x.mean.approx_eq(9.8075, F64Margin::default()));
+        assert!(x.sigma.approx_eq(statistics::stdev(&[9.81, 9.79, 9.83, 9.80]) / 2.0, F64Margin::default()));
+        assert_eq!(3.0, x.dof());
+        //Scaling by an exact number keeps the degrees of freedom
+        assert_eq!(3.0, (x * 2.0).dof());
+        assert_eq!(f64::INFINITY, Measurement::new(1.0, 0.1).dof());
+        let error = Measurement::from_samples(&[9.81]).unwrap_err();
+        assert_eq!("at least two samples are needed, got 1", error.message);
+        assert!(Measurement::from_samples(&[]).is_err());
+    }
+    #[test]
+    fn distributions() {
+        let margin = F64Margin::default();
+        assert!(Measurement::new(5.0, 0.02 / 3f64.sqrt()).approx_eq(Measurement::rectangular(5.0, 0.02), margin));
+        assert!(Measurement::new(5.0, 0.02 / 6f64.sqrt()).approx_eq(Measurement::triangular(5.0, 0.02), margin));
+        assert!(Measurement::new(5.0, 0.02 / 2f64.sqrt()).approx_eq(Measurement::u_shaped(5.0, 0.02), margin));
+        assert_eq!(Ok(Measurement::rectangular(5.0, 0.02)), Measurement::try_rectangular(5.0, 0.02));
+        assert!(Measurement::try_triangular(5.0, -0.02).is_err());
+        assert!(Measurement::try_u_shaped(5.0, f64::NAN).is_err());
+    }
+    #[test]
+    #[should_panic]
+    fn negative_half_width() {
+        Measurement::rectangular(5.0, -0.02);
+    }
+    #[test]
+    fn effective_dof() {
+        let x = Measurement::new(1.0, 0.1).with_dof(4.0);
+        let y = Measurement::new(2.0, 0.1).with_dof(4.0);
+        assert!((x + y).dof().approx_eq(8.0, F64Margin::default()));
+        //An exactly known uncertainty only adds to the combined uncertainty
+        let z = Measurement::new(2.0, 0.1);
+        assert!((x - z).dof().approx_eq(16.0, F64Margin::default()));
+        assert!(x.powf(2.0).dof().approx_eq(4.0, F64Margin::default()));
+
+        let expanded: f64 = Measurement::new(1.0, 0.1).with_dof(10.0).expanded_uncertainty(0.95);
+        assert!((expanded - 0.2228139).abs() < 1e-6);
+    }
+    #[test]
+    fn weighted_mean() {
+        let margin = F64Margin::default();
+        let combined = Measurement::weighted_mean(&[Measurement::new(1.0, 0.1), Measurement::new(2.0, 0.2)]).unwrap();
+        //Weights 100 and 25
+        assert!(combined.mean.approx_eq(Measurement::new(1.2, 125f64.sqrt().recip()), margin));
+        let chi_squared = (0.2f64 / 0.1).powi(2) + (0.8f64 / 0.2).powi(2);
+        assert!(combined.chi_squared.approx_eq(chi_squared, margin));
+        assert_eq!(1, combined.ndf);
+        assert!(combined.birge_ratio().approx_eq(chi_squared.sqrt(), margin));
+        assert!(combined.scaled().sigma.approx_eq(chi_squared.sqrt() / 125f64.sqrt(), margin));
+
+        let same = Measurement::weighted_mean(&[Measurement::new(3.0, 0.1), Measurement::new(3.0, 0.1)]).unwrap();
+        assert_eq!(0.0, same.chi_squared);
+        assert_eq!(1.0, same.p_value());
+        assert_eq!(same.mean, same.scaled());
+
+        let single = Measurement::weighted_mean(&[Measurement::new(3.0, 0.1)]).unwrap();
+        assert_eq!(0, single.ndf);
+        assert!(single.p_value().is_nan() && single.birge_ratio().is_nan());
+    }
+    #[test]
+    fn weighted_mean_of_exact_number() {
+        let error = Measurement::weighted_mean(&[Measurement::new(3.0, 0.1), Measurement::new(3.0, 0.0)]).unwrap_err();
+        assert_eq!("cannot weight a measurement without uncertainty: 3 ± 0", error.message);
+        assert!(Measurement::weighted_mean(&[Measurement::new(3.0, f64::NAN)]).is_err());
+        assert!(Measurement::weighted_mean(&[]).is_err());
+    }
+    #[test]
+    fn comparison() {
+        let margin = F64Margin::default();
+        let g = Measurement::new(9.79, 0.02).compare(&Measurement::new(9.80665, 0.0));
+        assert!(g.z_score.approx_eq((9.79 - 9.80665) / 0.02, margin));
+        assert!(g.agrees());
+        assert!(!g.clone().with_sigmas(0.5).agrees());
+        assert!((g.p_value - 0.405).abs() < 1e-3);
+
+        let z = Measurement::new(1.0, 0.3).compare(&Measurement::new(2.0, 0.4));
+        assert!(z.z_score.approx_eq(-2.0, margin));
+        assert!((z.p_value - 0.0455).abs() < 1e-4);
+        assert!(z.to_string().starts_with("z = -2, p = 0.0455"));
+        assert!(z.to_string().ends_with("(agree within 2σ)"));
+
+        let exact = Measurement::new(2.0, 0.0).compare(&Measurement::new(2.0, 0.0));
+        assert_eq!((0.0, 1.0, true), (exact.z_score, exact.p_value, exact.agrees()));
+        let exact = Measurement::new(2.0, 0.0).compare(&Measurement::new(3.0, 0.0));
+        assert_eq!((f64::NEG_INFINITY, 0.0, false), (exact.z_score, exact.p_value, exact.agrees()));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        use crate::value::Value;
+        let m = LabeledMeasurement::with_components(12.3, &[("stat", 0.4), ("syst", 0.2)]);
+        let json = serde_json::to_string(&m).unwrap();
+        assert_eq!(r#"{"mean":12.3,"sigma":0.447213595499958,"dof":null,"components":[["stat",0.4],["syst",0.2]]}"#, json);
+        assert_eq!(m, serde_json::from_str(&json).unwrap());
+        let m = Measurement::from_samples(&[1.0, 2.0, 3.0]).unwrap();
+        assert_eq!(m, serde_json::from_str(&serde_json::to_string(&m).unwrap()).unwrap());
+        assert_eq!(Measurement::new(1.0, 0.1), serde_json::from_str(r#"{"mean":1,"sigma":0.1}"#).unwrap());
+
+        let v = Value::Number(-2.5);
+        assert_eq!(r#"{"kind":"number","value":-2.5}"#, serde_json::to_string(&v).unwrap());
+        let v = Value::Comparison(Measurement::new(1.0, 0.1).compare(&Measurement::new(1.3, 0.0)));
+        assert_eq!(v, serde_json::from_str(&serde_json::to_string(&v).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!(Measurement::new(1.2, 0.1), "1.2 ± 0.1".parse().unwrap());
+        assert_eq!(Measurement::new(-1.2, 0.1), " -1.2+-0.1 ".parse().unwrap());
+        assert_eq!(Measurement::new(-1.2, 0.1), "-1.2 +/- 0.1".parse().unwrap());
+        assert_eq!(Measurement::new(1.5e3, 20.0), "1.5e3 ± 20".parse().unwrap());
+        assert_eq!(Measurement::new(2.0, 0.0), "2".parse().unwrap());
+        let m: LabeledMeasurement = "12.3 ± 0.4 (stat) ± 0.2 (syst)".parse().unwrap();
+        assert_eq!(LabeledMeasurement::with_components(12.3, &[("stat", 0.4), ("syst", 0.2)]), m);
+        assert_eq!(Measurement::new(12.3, 0.2f64.hypot(0.4)), "12.3 ± 0.4 (stat) ± 0.2 (syst)".parse().unwrap());
+    }
+
+    #[test]
+    fn concise_from_str() {
+        let margin = F64Margin { epsilon: 1e-15, ulps: 4 };
+        assert!("1.2(1)".parse::<Measurement>().unwrap().approx_eq(Measurement::new(1.2, 0.1), margin));
+        assert!("2.70(2)".parse::<Measurement>().unwrap().approx_eq(Measurement::new(2.7, 0.02), margin));
+        assert!("1234(56)".parse::<Measurement>().unwrap().approx_eq(Measurement::new(1234.0, 56.0), margin));
+        assert!("12.3(1.2)".parse::<Measurement>().unwrap().approx_eq(Measurement::new(12.3, 1.2), margin));
+        assert!("1.5e3(2)".parse::<Measurement>().unwrap().approx_eq(Measurement::new(1500.0, 200.0), margin));
+        assert!("-6.626e-34(3)".parse::<Measurement>().unwrap().approx_eq(Measurement::new(-6.626e-34, 3e-37), margin));
+        assert!("1200(3)".parse::<Measurement>().unwrap().approx_eq(Measurement::new(1200.0, 3.0), margin));
+    }
+
+    #[test]
+    fn invalid_from_str() {
+        for invalid in &["", "abc", "1 ± ", "1 ± -0.1", "1 ± x", "1.2(a)", "1 ± 0.1 (a b)", "1 ± 0.1 ± NaN"] {
+            assert!(invalid.parse::<Measurement>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn accessors() {
+        let m = Measurement::from_relative(-200.0, 0.01);
+        assert_eq!(Measurement::new(-200.0, 2.0), m);
+        assert_eq!((-200.0, 2.0, 4.0, 0.01), (m.mean(), m.sigma(), m.variance(), m.relative()));
+        assert_eq!(0.0, Measurement::exact(5.0).relative());
+        assert_eq!(f64::INFINITY, Measurement::new(0.0, 1.0).relative());
+    }
+
+    #[test]
+    fn try_new() {
+        assert_eq!(Measurement::new(1.0, 0.1), Measurement::try_new(1.0, 0.1).unwrap());
+        assert!(Measurement::try_new(1.0, -0.1).is_err());
+        assert!(Measurement::try_new(1.0, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn round_to_uncertainty() {
+        let m = Measurement::new(9.8123, 0.0234);
+        assert_eq!(("9.81".to_string(), "0.02".to_string()), m.round_to_uncertainty(1, Rounding::HalfEven));
+        assert_eq!(("9.812".to_string(), "0.023".to_string()), m.round_to_uncertainty(2, Rounding::HalfEven));
+        let m = Measurement::new(2.5, 1.0);
+        assert_eq!("2", m.round_to_uncertainty(1, Rounding::HalfEven).0);
+        assert_eq!("3", m.round_to_uncertainty(1, Rounding::HalfUp).0);
+        assert_eq!("-2", (-m).round_to_uncertainty(1, Rounding::TowardZero).0);
+        let m = Measurement::new(12.3456, 0.0996);
+        assert_eq!(("12.3".to_string(), "0.1".to_string()), m.round_to_uncertainty(1, Rounding::HalfEven));
+        let m = Measurement::new(12345.0, 234.0);
+        assert_eq!(("12300".to_string(), "200".to_string()), m.round_to_uncertainty(1, Rounding::HalfEven));
+        let m = Measurement::new(0.1 + 0.2, 0.05);
+        assert_eq!(("0.30".to_string(), "0.05".to_string()), m.round_to_uncertainty(1, Rounding::HalfEven));
+        let m = Measurement::<f32>::new(3.0, 0.014142135);
+        assert_eq!(("3.000".to_string(), "0.014".to_string()), m.round_to_uncertainty(2, Rounding::HalfEven));
+        assert_eq!(("2.5".to_string(), "0".to_string()), Measurement::exact(2.5).round_to_uncertainty(1, Rounding::HalfEven));
+    }
+
+    #[test]
+    fn single_precision() {
+        use float_cmp::F32Margin;
+        let x: Measurement<f32> = Measurement::new(1.0, 0.01);
+        let y = Measurement::new(2.0f32, 0.01);
+        let multiplied = Measurement::new(2.0, 2.0 * (0.01f32 / 1.0).hypot(0.01 / 2.0));
+        assert!(multiplied.approx_eq(x * y, F32Margin::default()));
+        assert!(Measurement::new(4.0f32, 0.08).approx_eq(x.powf(2.0) * 4.0, F32Margin::default()));
+        assert_eq!(LabeledMeasurement::with_components(1.5f32, &[("stat", 0.5)]), "1.5 ± 0.5 (stat)".parse().unwrap());
+        assert_eq!("3 ± 0.014142135", (x + y).to_string());
+    }
 }
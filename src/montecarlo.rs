@@ -0,0 +1,177 @@
+//!Propagation of uncertainties by Monte Carlo simulation
+//!
+//!Instead of expanding the expressions around the mean values of the measurements,
+//!every measurement is drawn at random from its distribution, and the expressions are
+//!evaluated once per draw. The mean and the covariance of the results are then those of
+//!the simulated values. This does not assume that the expressions are nearly linear, at
+//!the cost of a statistical error that decreases as the square root of the number of runs.
+
+use crate::environment::JointResult;
+use crate::error::Error;
+use crate::measurement::Measurement;
+use crate::parser::{expand, parse};
+use crate::propagation::{Inputs, Quantity};
+use crate::value::Value;
+use std::collections::HashMap;
+
+///Number of runs used when none is given
+pub const DEFAULT_RUNS: usize = 100_000;
+
+///Shape of the distribution from which a measurement is drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Distribution {
+    Normal,
+    Rectangular(f64), //half-width of the distribution
+    Triangular(f64),
+    UShaped(f64),
+}
+
+///Pseudo-random number generator (SplitMix64), small and good enough for simulations.
+///The same seed always gives the same sequence.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+    spare: Option<f64>, //Second normal variate of the last Box-Muller transform
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Rng {
+        Rng { state: seed, spare: None }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    ///Uniformly distributed in [0, 1)
+    fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    ///Standard normal variate, from the Box-Muller transform
+    fn normal(&mut self) -> f64 {
+        if let Some(z) = self.spare.take() {
+            return z;
+        }
+        let radius = (-2.0 * (1.0 - self.uniform()).ln()).sqrt();
+        let angle = 2.0 * std::f64::consts::PI * self.uniform();
+        self.spare = Some(radius * angle.sin());
+        radius * angle.cos()
+    }
+
+    ///Draws a value of the measurement
+    pub(crate) fn sample(&mut self, m: &Measurement, distribution: Distribution) -> f64 {
+        match distribution {
+            Distribution::Normal => m.mean + m.sigma * self.normal(),
+            Distribution::Rectangular(a) => m.mean + a * (2.0 * self.uniform() - 1.0),
+            Distribution::Triangular(a) => m.mean + a * (self.uniform() + self.uniform() - 1.0),
+            Distribution::UShaped(a) => m.mean + a * (std::f64::consts::PI * self.uniform()).cos(),
+        }
+    }
+}
+
+///Evaluates the expressions `runs` times, after the definitions, drawing every measurement
+///at random each time. Returns the means, uncertainties and covariance matrix of the results.
+///
+///Measurements written as `x ± u` are normally distributed, while the Type B measurements
+///`rect`, `tri` and `ushape` are drawn from their own distributions. The definitions are
+///pairs of a name and an expression, which can be used by the following ones.
+///
+///Example:
+///```
+///use scicalc_rs::montecarlo::simulate;
+///
+///let result = simulate(&[("x", "1 ± 0.1")], &["x^2"], 10_000, 42).unwrap();
+///assert!((result.means[0] - 1.01).abs() < 0.01);
+///```
+pub fn simulate(definitions: &[(&str, &str)], expressions: &[&str], runs: usize, seed: u64) -> Result<JointResult, Error> {
+    if runs < 2 {
+        return Err(Error::unlocated(format!("a simulation needs at least two runs, got {}", runs)));
+    }
+    let definitions = definitions
+        .iter()
+        .map(|(name, input)| Ok((*name, parse(input)?)))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let trees = expressions.iter().map(|input| parse(input)).collect::<Result<Vec<_>, Error>>()?;
+
+    let mut rng = Rng::new(seed);
+    let mut values: Vec<Vec<f64>> = vec![Vec::with_capacity(runs); expressions.len()];
+    for _ in 0..runs {
+        let mut inputs = Inputs::sampling(rng);
+        let mut variables = HashMap::new();
+        for (name, s) in &definitions {
+            let value = expand(s, &variables, &mut inputs)?;
+            variables.insert(name.to_string(), value);
+        }
+        for (i, s) in trees.iter().enumerate() {
+            match expand(s, &variables, &mut inputs)? {
+                Quantity::Exact(Value::Number(x)) | Quantity::Exact(Value::PosNumber(x)) => values[i].push(x),
+                Quantity::Exact(Value::Rational(x)) => values[i].push(x.to_f64()),
+                _ => return Err(Error::new("the result is not a number", s.span())),
+            }
+        }
+        rng = inputs.into_rng();
+    }
+
+    let means: Vec<f64> = values.iter().map(|v| v.iter().sum::<f64>() / runs as f64).collect();
+    let covariance: Vec<Vec<f64>> = (0..values.len())
+        .map(|i| {
+            (0..values.len())
+                .map(|j| {
+                    let products = values[i].iter().zip(&values[j]).map(|(x, y)| (x - means[i]) * (y - means[j]));
+                    products.sum::<f64>() / (runs - 1) as f64
+                })
+                .collect()
+        })
+        .collect();
+    Ok(JointResult {
+        names: expressions.iter().map(|input| input.to_string()).collect(),
+        means,
+        sigmas: (0..values.len()).map(|i| covariance[i][i].sqrt()).collect(),
+        covariance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_expression() {
+        let result = simulate(&[("a", "2 ± 0.1"), ("b", "1 ± 0.2")], &["a + b", "a - b"], 100_000, 1).unwrap();
+        assert!((result.means[0] - 3.0).abs() < 0.005);
+        assert!((result.sigmas[0] - 0.05_f64.sqrt()).abs() < 0.005);
+        //cov(a + b, a - b) = σa² - σb²
+        assert!((result.covariance[0][1] - -0.03).abs() < 0.002);
+    }
+
+    #[test]
+    fn test_distributions() {
+        let mut rng = Rng::new(7);
+        let m = Measurement::new(5.0, 0.0);
+        for &distribution in &[Distribution::Rectangular(0.5), Distribution::Triangular(0.5), Distribution::UShaped(0.5)] {
+            let samples: Vec<f64> = (0..1000).map(|_| rng.sample(&m, distribution)).collect();
+            assert!(samples.iter().all(|&x| (4.5..=5.5).contains(&x)));
+        }
+        let result = simulate(&[], &["rect(5, 0.5)"], 100_000, 3).unwrap();
+        assert!((result.sigmas[0] - 0.5 / 3f64.sqrt()).abs() < 0.002);
+    }
+
+    #[test]
+    fn test_seed() {
+        let run = |seed| simulate(&[], &["(1 ± 0.5)^3"], 1000, seed).unwrap();
+        assert_eq!(run(11), run(11));
+        assert_ne!(run(11), run(12));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(simulate(&[], &["1 ± 0.1 ~= 2"], 10, 0).is_err());
+        assert!(simulate(&[], &["1 +"], 10, 0).is_err());
+        assert!(simulate(&[], &["1"], 1, 0).is_err());
+    }
+}
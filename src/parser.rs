@@ -1,25 +1,56 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fmt;
-use crate::{lexer::Lexer, measurement::Measurement, value::Value};
+use crate::{lexer::Lexer, value::Value};
+use crate::error::{Error, Span};
 use crate::token::Token;
-use std::panic;
+use crate::montecarlo::Distribution;
+use crate::propagation::{Inputs, Order, Quantity};
+use crate::measurement::{Comparison, Measurement};
+use crate::rational::Rational;
+use crate::decimal::DecimalNumber;
+use crate::statistics;
 
 ///An expression, stored as a tree structure
 ///
 ///Look into "S-expressions" to learn more
 ///
 ///Reference: https://en.wikipedia.org/wiki/S-expression
-#[derive(Debug)]
-enum S {
-    Atom(Token), //A single token
-    Group(Token, Vec<S>) //An operator and a list of tokens
+///
+///With the `serde` feature, atoms are serialized as `{"atom": [token, span]}` and groups
+///as `{"group": [operator, operands, span]}`, e.g. `2 * x` is, in JSON:
+///
+///```text
+///{"group": ["mul", [{"atom": [{"pos_num": "2"}, {"start": 0, "end": 1}]},
+///                   {"atom": [{"ident": "x"}, {"start": 4, "end": 5}]}], {"start": 0, "end": 5}]}
+///```
+///
+///Function calls are groups whose operator is the name of the function, e.g. `{"ident": "mean"}`,
+///and lists are groups whose operator is `"left_bracket"`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+pub enum S {
+    Atom(Token, Span), //A single token
+    Group(Token, Vec<S>, Span) //An operator and a list of tokens
+}
+
+impl S {
+    ///Where the (sub-)expression is in the input
+    pub fn span(&self) -> Span {
+        match self {
+            S::Atom(_, span) | S::Group(_, _, span) => *span,
+        }
+    }
 }
 
 
 impl fmt::Display for S {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            S::Atom(i) => write!(f, "{}", i),
-            S::Group(head, rest) => {
+            S::Atom(i, _) => write!(f, "{}", i),
+            S::Group(head, rest, _) => {
                 write!(f, "({}", head)?;
                 for s in rest {
                     write!(f, " {}", s)?
@@ -30,56 +61,134 @@ impl fmt::Display for S {
     }
 }
 
-fn expr(text: &str) -> S {
-    let mut lexer = Lexer::new(text);
-    expr_bp(&mut lexer, 0)
+///Parses the expression, panicking if it is not valid
+#[cfg(test)]
+pub(crate) fn expr(text: &str) -> S {
+    match parse(text) {
+        Ok(s) => s,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+///Parses the expression into a tree, or returns the first error found in it
+pub fn parse(text: &str) -> Result<S, Error> {
+    let mut lexer = Lexer::try_new(text)?;
+    let s = expr_bp(&mut lexer, 0)?;
+    match lexer.peek() {
+        Token::Eof => Ok(s),
+        t => Err(Error::new(format!("unexpected token: \'{}\'", t), lexer.span())),
+    }
+}
+
+///Returns true if the next tokens are a label for an uncertainty, such as `(stat)`
+fn at_label(lexer: &Lexer) -> bool {
+    matches!(
+        (lexer.peek_nth(0), lexer.peek_nth(1), lexer.peek_nth(2)),
+        (Token::LeftParen, Token::Ident(_), Token::RightParen)
+    )
+}
+
+///Consumes the next token, which must be `expected`
+fn expect(lexer: &mut Lexer, expected: Token) -> Result<Span, Error> {
+    let span = lexer.span();
+    match lexer.next() {
+        t if t == expected => Ok(span),
+        Token::Eof => Err(Error::new(format!("expected \'{}\', found the end of the expression", expected), span)),
+        t => Err(Error::new(format!("expected \'{}\', found \'{}\'", expected, t), span)),
+    }
 }
 
 ///Parses the expressions using Pratt's method(TDOP).
-fn expr_bp(lexer: &mut Lexer, min_bp: u8) -> S {
+fn expr_bp(lexer: &mut Lexer, min_bp: u8) -> Result<S, Error> {
+    let start = lexer.span();
     let first_token = lexer.next();
     let mut lhs = match first_token {
-        Token::PosNum(_) | Token::EulersNum | Token::Pi => {
-            S::Atom(first_token)
+        Token::Ident(_) if lexer.peek() == Token::LeftParen => {
+            //Function call, e.g. 'mean(9.81, 9.79, 9.83)'
+            lexer.next();
+            let (arguments, end) = list(lexer, Token::RightParen)?;
+            S::Group(first_token, arguments, start.to(end))
+        },
+        Token::LeftBracket => {
+            let (items, end) = list(lexer, Token::RightBracket)?;
+            S::Group(first_token, items, start.to(end))
+        },
+        Token::PosNum(_) | Token::Ident(_) | Token::EulersNum | Token::Pi => {
+            S::Atom(first_token, start)
         },
         Token::LeftParen => {
-            let lhs = expr_bp(lexer, 0);
-            assert_eq!(lexer.next(), Token::RightParen);
+            let lhs = expr_bp(lexer, 0)?;
+            expect(lexer, Token::RightParen)?;
             lhs
         },
         Token::Minus => {
             let ((), r_bp) = prefix_binding_power(&first_token);
-            let rhs = expr_bp(lexer, r_bp);
-            S::Group(first_token, vec![rhs])
+            let rhs = expr_bp(lexer, r_bp)?;
+            let span = start.to(rhs.span());
+            S::Group(first_token, vec![rhs], span)
         },
-        t => panic!("bad token(lhs): {:?}", t),
+        Token::Eof => return Err(Error::new("unexpected end of the expression", start)),
+        t => return Err(Error::new(format!("unexpected token: \'{}\'", t), start)),
     };
-    
+
     loop {
         let token = lexer.peek();
         let op = match token {
             Token::Eof => break,
-            Token::Add | Token::Minus | Token::Mul | Token::Div |
-            Token::RightParen | Token::PlusMinus => token,
-            Token::LeftParen => panic!("Excess left parenthesis \'(\'"),
-            t => panic!("bad token(rhs): {:?}", t),
+            Token::Add | Token::Minus | Token::Mul | Token::Div | Token::Caret | Token::Approx |
+            Token::RightParen | Token::PlusMinus | Token::Comma | Token::RightBracket => token,
+            Token::LeftParen if at_label(lexer) => break,
+            Token::LeftParen => return Err(Error::new("Excess left parenthesis \'(\'", lexer.span())),
+            t => return Err(Error::new(format!("unexpected token: \'{}\'", t), lexer.span())),
         };
         if let Some((l_bp, r_bp)) = infix_binding_power(&op) {
             if l_bp < min_bp {
                 break;
             }
-    
+
             lexer.next();
-            let rhs = expr_bp(lexer, r_bp);
-    
-            lhs = S::Group(op, vec![lhs, rhs]);
+            let rhs = expr_bp(lexer, r_bp)?;
+            let span = lhs.span().to(rhs.span());
+
+            if op == Token::PlusMinus && at_label(lexer) {
+                //Labeled uncertainty, e.g. '± 0.2 (syst)'
+                lexer.next();
+                let label_span = lexer.span();
+                let label = lexer.next();
+                let end = lexer.span();
+                lexer.next();
+                lhs = S::Group(op, vec![lhs, rhs, S::Atom(label, label_span)], span.to(end));
+            } else {
+                lhs = S::Group(op, vec![lhs, rhs], span);
+            }
         } else {
             //Stop parsing
             break;
         }
     }
 
-    lhs
+    Ok(lhs)
+}
+
+///Parses a comma-separated list of expressions, up to and including the `closing` token.
+///Also returns where the list ends.
+fn list(lexer: &mut Lexer, closing: Token) -> Result<(Vec<S>, Span), Error> {
+    let mut items = Vec::new();
+    if lexer.peek() == closing {
+        let end = lexer.span();
+        lexer.next();
+        return Ok((items, end));
+    }
+    loop {
+        items.push(expr_bp(lexer, 0)?);
+        let span = lexer.span();
+        match lexer.next() {
+            Token::Comma => continue,
+            t if t == closing => return Ok((items, span)),
+            Token::Eof => return Err(Error::new(format!("expected \'{}\', found the end of the expression", closing), span)),
+            t => return Err(Error::new(format!("expected \',\' or \'{}\', found \'{}\'", closing, t), span)),
+        }
+    }
 }
 
 fn prefix_binding_power(op: &Token) -> ((), u8) { 
@@ -97,97 +206,417 @@ fn prefix_binding_power(op: &Token) -> ((), u8) {
 ///If the operator is not valid, returns None.
 fn infix_binding_power(op: &Token) -> Option<(u8, u8)> {
     let res = match op {
+        Token::Approx => (0, 1), //binds looser than any arithmetic
         Token::Add | Token::Minus => (1, 2),
         Token::Mul | Token::Div => (3, 4),
         Token::PlusMinus => (7,8),
+        Token::Caret => (11, 10), //right-associative, binds tighter than the prefix minus
         _ => return None,
     };
     Some(res)
 }
 
+///Arithmetic computed alongside the evaluation of an expression, such as the exact mean
+///of decimal arithmetic or the significant figures of a result. The evaluation itself,
+///i.e. variables, measurements and functions, is shared: every hook is called once its
+///own quantity has been evaluated, with the backend's values of the operands.
+///
+///The plain evaluation has the backend `()`.
+pub(crate) trait Backend {
+    type Value: Clone;
+
+    ///The value of a variable
+    fn variable(&self, name: &str, span: Span) -> Result<Self::Value, Error>;
+    ///A number, as it is written
+    fn number(&self, x: &DecimalNumber, span: Span) -> Result<Self::Value, Error>;
+    ///A constant, 'e' or 'π'
+    fn constant(&self, x: f64, span: Span) -> Result<Self::Value, Error>;
+    ///One of '+', '-', '*', '/' and '^', or the unary '-' with a single operand
+    fn operation(&self, op: &Token, operands: &[Self::Value], span: Span) -> Result<Self::Value, Error>;
+    ///A call to a built-in function with its arguments, after flattening lists.
+    ///'x ± u' is the function "±" of the mean and the uncertainty, and 'a ~= b' is 'compare(a, b)'.
+    fn function(&self, name: &str, arguments: &[Self::Value], span: Span) -> Result<Self::Value, Error>;
+}
+
+impl Backend for () {
+    type Value = ();
+
+    fn variable(&self, _: &str, _: Span) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn number(&self, _: &DecimalNumber, _: Span) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn constant(&self, _: f64, _: Span) -> Result<(), Error> {
+        Ok(())
+    }
 
-fn eval_expr(expression: &S) -> Value {
+    fn operation(&self, _: &Token, _: &[()], _: Span) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn function(&self, _: &str, _: &[()], _: Span) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+///Evaluates an operand of an arithmetic operator, which can't be a comparison
+fn operand<B: Backend>(
+    expression: &S,
+    variables: &HashMap<String, Quantity>,
+    inputs: &mut Inputs,
+    backend: &B,
+) -> Result<(Quantity, B::Value), Error> {
+    match expand_with(expression, variables, inputs, backend)? {
+        (Quantity::Exact(Value::Comparison(_)), _) => Err(Error::new("a comparison can't be used in arithmetic", expression.span())),
+        result => Ok(result),
+    }
+}
+
+///Evaluates the expression tree.
+///
+///Names are looked up in `variables`, and every measurement found
+///along the way is registered in `inputs`.
+pub(crate) fn expand(expression: &S, variables: &HashMap<String, Quantity>, inputs: &mut Inputs) -> Result<Quantity, Error> {
+    Ok(expand_with(expression, variables, inputs, &())?.0)
+}
+
+///Evaluates the expression tree like `expand`, computing its value in the arithmetic of
+///`backend` as well
+pub(crate) fn expand_with<B: Backend>(
+    expression: &S,
+    variables: &HashMap<String, Quantity>,
+    inputs: &mut Inputs,
+    backend: &B,
+) -> Result<(Quantity, B::Value), Error> {
     match expression {
-        S::Atom(token) => {
+        S::Atom(token, span) => {
             match token {
-                Token::PosNum(x) => Value::PosNumber(x.as_float()),
-                Token::EulersNum => Value::Number(std::f64::consts::E),
-                Token::Pi => Value::Number(std::f64::consts::PI),
-                _ => panic!("bad token(eval atom): {:?}", token)
+                //Integers are exact, so that e.g. '1/3 * 3' is exactly 1
+                Token::PosNum(x) => {
+                    let q = match x.full_number().parse::<i128>() {
+                        Ok(n) => Quantity::Exact(Value::Rational(Rational::from(n))),
+                        Err(_) => Quantity::Exact(Value::PosNumber(x.as_float())),
+                    };
+                    Ok((q, backend.number(x, *span)?))
+                },
+                Token::EulersNum => {
+                    let e = std::f64::consts::E;
+                    Ok((Quantity::Exact(Value::Number(e)), backend.constant(e, *span)?))
+                },
+                Token::Pi => {
+                    let pi = std::f64::consts::PI;
+                    Ok((Quantity::Exact(Value::Number(pi)), backend.constant(pi, *span)?))
+                },
+                Token::Ident(name) => {
+                    match variables.get(name) {
+                        Some(q) => Ok((q.clone(), backend.variable(name, *span)?)),
+                        None => Err(Error::new(format!("unknown variable: \'{}\'", name), *span))
+                    }
+                },
+                _ => Err(Error::new(format!("bad token(eval atom): {:?}", token), *span))
             }
         },
-        S::Group(op, sub_expressions) => {
+        S::Group(op, sub_expressions, span) => {
+            let arity = |n: usize, name: &str| -> Result<(), Error> {
+                if sub_expressions.len() == n {
+                    Ok(())
+                } else {
+                    Err(Error::new(format!("bad sub-expressions, {} operator takes {} operands", name, n), *span))
+                }
+            };
             match op {
                 Token::Add => {
-                    if sub_expressions.len() != 2 {
-                        panic!("bad sub-expressions: {:?}, addition ('+') operator is binary.", sub_expressions)
-                    } else {
-                        let lhs = eval_expr(&sub_expressions[0]);
-                        let rhs = eval_expr(&sub_expressions[1]);
-                        lhs + rhs
-                    }
+                    arity(2, "addition ('+')")?;
+                    let (lhs, x) = operand(&sub_expressions[0], variables, inputs, backend)?;
+                    let (rhs, y) = operand(&sub_expressions[1], variables, inputs, backend)?;
+                    Ok((lhs + rhs, backend.operation(op, &[x, y], *span)?))
                 },
                 Token::Minus => {
                     if sub_expressions.len() == 1 {
                         //Unary minus operator
-                        - eval_expr(&sub_expressions[0])
-                    } else if sub_expressions.len() != 2 {
-                        panic!("bad sub-expressions: {:?}, subtraction ('-') operator is binary.", sub_expressions)
+                        let (q, x) = operand(&sub_expressions[0], variables, inputs, backend)?;
+                        Ok((- q, backend.operation(op, &[x], *span)?))
                     } else {
-                        let lhs = eval_expr(&sub_expressions[0]);
-                        let rhs = eval_expr(&sub_expressions[1]);
-                        lhs - rhs
+                        arity(2, "subtraction ('-')")?;
+                        let (lhs, x) = operand(&sub_expressions[0], variables, inputs, backend)?;
+                        let (rhs, y) = operand(&sub_expressions[1], variables, inputs, backend)?;
+                        Ok((lhs - rhs, backend.operation(op, &[x, y], *span)?))
                     }
                 },
                 Token::Mul => {
-                    if sub_expressions.len() != 2 {
-                        panic!("bad sub-expressions: {:?}, multiplication ('*') operator is binary.", sub_expressions)
-                    } else {
-                        let lhs = eval_expr(&sub_expressions[0]);
-                        let rhs = eval_expr(&sub_expressions[1]);
-                        lhs * rhs
-                    }
+                    arity(2, "multiplication ('*')")?;
+                    let (lhs, x) = operand(&sub_expressions[0], variables, inputs, backend)?;
+                    let (rhs, y) = operand(&sub_expressions[1], variables, inputs, backend)?;
+                    Ok((lhs * rhs, backend.operation(op, &[x, y], *span)?))
                 },
                 Token::Div => {
-                    if sub_expressions.len() != 2 {
-                        panic!("bad sub-expressions: {:?}, division ('/') operator is binary.", sub_expressions)
-                    } else {
-                        let lhs = eval_expr(&sub_expressions[0]);
-                        let rhs = eval_expr(&sub_expressions[1]);
-                        lhs / rhs
-                    }
+                    arity(2, "division ('/')")?;
+                    let (lhs, x) = operand(&sub_expressions[0], variables, inputs, backend)?;
+                    let (rhs, y) = operand(&sub_expressions[1], variables, inputs, backend)?;
+                    Ok((lhs / rhs, backend.operation(op, &[x, y], *span)?))
+                },
+                Token::Caret => {
+                    arity(2, "exponentiation ('^')")?;
+                    let (lhs, x) = operand(&sub_expressions[0], variables, inputs, backend)?;
+                    let (rhs, y) = operand(&sub_expressions[1], variables, inputs, backend)?;
+                    Ok((lhs.pow(rhs), backend.operation(op, &[x, y], *span)?))
                 },
                 Token::PlusMinus => {
-                    if sub_expressions.len() != 2 {
-                        panic!("bad sub-expressions: {:?}, plus-minus ('±') operator is binary.", sub_expressions)
-                    } else {
-                        let lhs = eval_expr(&sub_expressions[0]);
-                        let rhs = eval_expr(&sub_expressions[1]);
-                        let x = match lhs {
-                            Value::Number(m) | Value::PosNumber(m) => m,
-                            _ => panic!("left-hand side is not a number! lhs: {:?}", sub_expressions[0])
-                        };
-                        let y = match rhs {
-                            Value::PosNumber(m) => m,
-                            _ => panic!("right-hand side is not a positive number! rhs: {:?}", sub_expressions[1])
-                        };
-                        Value::Measurement(Measurement::new(x, y))
+                    if sub_expressions.len() != 3 {
+                        arity(2, "plus-minus ('±')")?;
                     }
+                    let (lhs, x) = operand(&sub_expressions[0], variables, inputs, backend)?;
+                    let (rhs, u) = operand(&sub_expressions[1], variables, inputs, backend)?;
+                    let y = match rhs {
+                        Quantity::Exact(Value::Number(m)) | Quantity::Exact(Value::PosNumber(m)) if m >= 0.0 => m,
+                        Quantity::Exact(Value::Rational(m)) if m.numerator() >= 0 => m.to_f64(),
+                        _ => return Err(Error::new("the uncertainty must be a non-negative number", sub_expressions[1].span()))
+                    };
+                    let label = match sub_expressions.get(2) {
+                        Some(S::Atom(Token::Ident(label), _)) => label.as_str(),
+                        Some(s) => return Err(Error::new(format!("bad label: {}", s), s.span())),
+                        None => "",
+                    };
+                    let q = match lhs {
+                        Quantity::Exact(Value::Number(m)) | Quantity::Exact(Value::PosNumber(m)) => inputs.push(m, y, label),
+                        Quantity::Exact(Value::Rational(m)) => inputs.push(m.to_f64(), y, label),
+                        //Chained uncertainties, e.g. '12.3 ± 0.4 (stat) ± 0.2 (syst)',
+                        //add an independent error to the measurement
+                        Quantity::Uncertain(_) => lhs + inputs.push(0.0, y, label),
+                        _ => return Err(Error::new("the mean value must be a number", sub_expressions[0].span()))
+                    };
+                    Ok((q, backend.function("±", &[x, u], *span)?))
                 },
-                _ => todo!()
+                Token::Approx => {
+                    arity(2, "comparison ('~=')")?;
+                    let (lhs, x) = operand(&sub_expressions[0], variables, inputs, backend)?;
+                    let (rhs, y) = operand(&sub_expressions[1], variables, inputs, backend)?;
+                    Ok((compare(lhs, rhs, inputs, *span)?, backend.function("compare", &[x, y], *span)?))
+                },
+                Token::Ident(name) => call(name, sub_expressions, *span, variables, inputs, backend),
+                Token::LeftBracket => Err(Error::new("a list can only be the argument of a function", *span)),
+                _ => Err(Error::new(format!("unknown operator: \'{}\'", op), *span)),
+            }
+        }
+    }
+}
+
+///Evaluates a call to one of the built-in functions
+fn call<B: Backend>(
+    name: &str,
+    arguments: &[S],
+    span: Span,
+    variables: &HashMap<String, Quantity>,
+    inputs: &mut Inputs,
+    backend: &B,
+) -> Result<(Quantity, B::Value), Error> {
+    let (q, values) = match name {
+        //Type A evaluation of repeated readings
+        "mean" | "stdev" | "sem" => {
+            let (samples, values) = samples(arguments, variables, inputs, backend)?;
+            if samples.len() < 2 {
+                return Err(Error::new(format!("{} needs at least two samples, got {}", name, samples.len()), span));
             }
+            let q = match name {
+                "mean" => inputs.push_measurement(&Measurement::from_samples(&samples)?),
+                "stdev" => Quantity::Exact(Value::PosNumber(statistics::stdev(&samples))),
+                _ => Quantity::Exact(Value::PosNumber(statistics::sem(&samples))),
+            };
+            (q, values)
+        },
+        //'compare(a, b)' or 'compare(a, b, n)', judging agreement at n sigmas
+        "compare" => {
+            let (quantities, values): (Vec<Quantity>, Vec<B::Value>) =
+                evaluate_all(arguments, variables, inputs, backend)?.into_iter().unzip();
+            let mut quantities = quantities.into_iter();
+            let sigmas = match (quantities.next(), quantities.next(), quantities.next(), quantities.next()) {
+                (Some(a), Some(b), None, None) => (a, b, None),
+                (Some(a), Some(b), Some(Quantity::Exact(Value::Rational(n))), None) if n.numerator() > 0 => (a, b, Some(n.to_f64())),
+                (Some(a), Some(b), Some(Quantity::Exact(Value::Number(n))), None)
+                | (Some(a), Some(b), Some(Quantity::Exact(Value::PosNumber(n))), None) if n > 0.0 => (a, b, Some(n)),
+                _ => return Err(Error::new("compare expects two values and, optionally, a positive number of sigmas", span)),
+            };
+            let q = match (compare(sigmas.0, sigmas.1, inputs, span)?, sigmas.2) {
+                (Quantity::Exact(Value::Comparison(c)), Some(n)) => Quantity::Exact(Value::Comparison(c.with_sigmas(n))),
+                (q, _) => q,
+            };
+            (q, values)
+        },
+        "wmean" => {
+            let (measurements, values): (Vec<Quantity>, Vec<B::Value>) =
+                evaluate_all(arguments, variables, inputs, backend)?.into_iter().unzip();
+            (weighted_mean(measurements, inputs, span)?.0, values)
+        },
+        //Type B evaluation of a half-width 'a', e.g. 'rect(5.00, 0.02)'
+        "rect" | "tri" | "ushape" => {
+            let (samples, values) = samples(arguments, variables, inputs, backend)?;
+            let (mean, a) = match samples[..] {
+                [mean, a] if a >= 0.0 => (mean, a),
+                [_, _] => return Err(Error::new("the half-width must be a non-negative number", arguments[1].span())),
+                _ => return Err(Error::new(format!("{} expects the mean value and a half-width", name), span)),
+            };
+            let (measurement, distribution) = match name {
+                "rect" => (Measurement::rectangular(mean, a), Distribution::Rectangular(a)),
+                "tri" => (Measurement::triangular(mean, a), Distribution::Triangular(a)),
+                _ => (Measurement::u_shaped(mean, a), Distribution::UShaped(a)),
+            };
+            (inputs.push_distribution(&measurement, distribution), values)
+        },
+        _ => return Err(Error::new(format!("unknown function: \'{}\'", name), span)),
+    };
+    Ok((q, backend.function(name, &values, span)?))
+}
+
+///Evaluates the arguments of a function, flattening lists,
+///e.g. both 'mean(1, 2, 3)' and 'mean([1, 2, 3])' have the arguments 1, 2 and 3
+fn evaluate_all<B: Backend>(
+    expressions: &[S],
+    variables: &HashMap<String, Quantity>,
+    inputs: &mut Inputs,
+    backend: &B,
+) -> Result<Vec<(Quantity, B::Value)>, Error> {
+    let mut result = Vec::new();
+    for expression in expressions {
+        match expression {
+            S::Group(Token::LeftBracket, items, _) => result.extend(evaluate_all(items, variables, inputs, backend)?),
+            _ => result.push(operand(expression, variables, inputs, backend)?),
+        }
+    }
+    Ok(result)
+}
+
+///Evaluates the arguments of a statistical function, which must be exact numbers
+fn samples<B: Backend>(
+    expressions: &[S],
+    variables: &HashMap<String, Quantity>,
+    inputs: &mut Inputs,
+    backend: &B,
+) -> Result<(Vec<f64>, Vec<B::Value>), Error> {
+    let mut result = (Vec::new(), Vec::new());
+    for expression in expressions {
+        match expression {
+            S::Group(Token::LeftBracket, items, _) => {
+                let (samples, values) = samples(items, variables, inputs, backend)?;
+                result.0.extend(samples);
+                result.1.extend(values);
+            },
+            _ => match operand(expression, variables, inputs, backend)? {
+                (Quantity::Exact(Value::Number(x)), value) | (Quantity::Exact(Value::PosNumber(x)), value) => {
+                    result.0.push(x);
+                    result.1.push(value);
+                },
+                (Quantity::Exact(Value::Rational(x)), value) => {
+                    result.0.push(x.to_f64());
+                    result.1.push(value);
+                },
+                _ => return Err(Error::new("samples must be exact numbers", expression.span())),
+            },
         }
     }
+    Ok(result)
 }
 
+///Compares two values from the z-score of their difference. Correlations between
+///them are taken into account.
+fn compare(lhs: Quantity, rhs: Quantity, inputs: &Inputs, span: Span) -> Result<Quantity, Error> {
+    if inputs.is_sampling() {
+        return Err(Error::new("comparisons are not supported in Monte Carlo mode", span));
+    }
+    let comparison = match Measurement::try_from(inputs.value(lhs - rhs, Order::First)) {
+        Ok(d) => Comparison::new(d.mean, d.sigma, d.dof),
+        Err(e) => return Err(Error::new(e.message, span)),
+    };
+    Ok(Quantity::Exact(Value::Comparison(comparison)))
+}
+
+///Evaluates a call to `wmean`, returning the weighted mean with the chi-square of its
+///arguments around it and its degrees of freedom. None if the expression is not such a call.
+pub(crate) fn expand_weighted_mean(
+    expression: &S,
+    variables: &HashMap<String, Quantity>,
+    inputs: &mut Inputs,
+) -> Result<Option<(Quantity, f64, usize)>, Error> {
+    match expression {
+        S::Group(Token::Ident(name), arguments, span) if name == "wmean" => {
+            let (measurements, _): (Vec<Quantity>, Vec<()>) = evaluate_all(arguments, variables, inputs, &())?.into_iter().unzip();
+            let ndf = measurements.len().saturating_sub(1);
+            let (mean, chi_squared) = weighted_mean(measurements, inputs, *span)?;
+            Ok(Some((mean, chi_squared, ndf)))
+        },
+        _ => Ok(None),
+    }
+}
+
+///Inverse-variance weighted mean of the arguments, and their chi-square around it.
+///Correlations between them and the inputs are kept, since the mean is a linear
+///combination of the arguments.
+fn weighted_mean(arguments: Vec<Quantity>, inputs: &Inputs, span: Span) -> Result<(Quantity, f64), Error> {
+    if inputs.is_sampling() {
+        return Err(Error::new("wmean is not supported in Monte Carlo mode", span));
+    }
+    let variances: Vec<f64> = arguments.iter().map(|x| inputs.covariance(x, x, Order::First)).collect();
+    if arguments.is_empty() || variances.iter().any(|&v| v <= 0.0) {
+        return Err(Error::new("wmean needs at least one argument, all with uncertainties", span));
+    }
+    let total: f64 = variances.iter().map(|v| 1.0 / v).sum();
+    let means: Vec<f64> = arguments.iter().map(|x| inputs.mean(x, Order::First)).collect();
+    let mean = arguments
+        .into_iter()
+        .zip(&variances)
+        .map(|(x, v)| x * Quantity::Exact(Value::Number(1.0 / (v * total))))
+        .reduce(|x, y| x + y)
+        .unwrap();
+    let center = inputs.mean(&mean, Order::First);
+    let chi_squared = means.iter().zip(&variances).map(|(x, v)| (x - center).powi(2) / v).sum();
+    Ok((mean, chi_squared))
+}
+
+#[cfg(test)]
+pub(crate) fn eval_expr(expression: &S) -> Value {
+    let mut inputs = Inputs::default();
+    match expand(expression, &HashMap::new(), &mut inputs) {
+        Ok(result) => inputs.value(result, Order::First),
+        Err(e) => panic!("{}", e),
+    }
+}
+
+///Evaluates the expression, panicking if it is not valid. See `try_eval`.
 pub fn eval(input: &str) -> Value {
-    let s = expr(input);
-    eval_expr(&s)
+    eval_with(input, Order::First)
+}
+
+///Evaluates the expression, propagating uncertainties with
+///a Taylor expansion of the given order.
+///
+///`eval(input)` is the same as `eval_with(input, Order::First)`
+pub fn eval_with(input: &str, order: Order) -> Value {
+    match try_eval_with(input, order) {
+        Ok(value) => value,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+///Evaluates the expression, or returns the first error found in it
+pub fn try_eval(input: &str) -> Result<Value, Error> {
+    try_eval_with(input, Order::First)
+}
+
+///Same as `eval_with`, but returns the first error found in the expression
+pub fn try_eval_with(input: &str, order: Order) -> Result<Value, Error> {
+    let s = parse(input)?;
+    let mut inputs = Inputs::default();
+    let result = expand(&s, &HashMap::new(), &mut inputs)?;
+    Ok(inputs.value(result, order))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use float_cmp::{ApproxEq, F64Margin};
+    use crate::measurement::LabeledMeasurement;
     #[test]
     fn tests() {
         let s = expr("1 + 2 * 3");
@@ -200,9 +629,25 @@ mod tests {
         assert_eq!(s.to_string(), "(* (± 1 2) 3)");
     }
     #[test]
+    fn test_power() {
+        let s = expr("2 ^ 3 ^ 2");
+        assert_eq!(s.to_string(), "(^ 2 (^ 3 2))");
+        let s = expr("-2 ^ 2 * 3");
+        assert_eq!(s.to_string(), "(* (- (^ 2 2)) 3)");
+        let s = expr("(1.0 ± 0.1) ^ 2");
+        assert_eq!(s.to_string(), "(^ (± 1.0 0.1) 2)");
+        match eval("2 ^ 10") {
+            Value::Rational(x) => assert_eq!(x, Rational::from(1024)),
+            _ => panic!("Error")
+        }
+    }
+    #[test]
     fn test_negative() {
         let s = expr("-1.0 ± 2.0");
         assert_eq!(s.to_string(), "(± (- 1.0) 2.0)");
+        //'+-' is an addition of a negative number, not '±'
+        assert_eq!(Value::Rational(Rational::from(2)), eval("3+-1"));
+        assert_eq!(expr("2 ± 1").to_string(), expr("2 +/- 1").to_string());
     }
     #[test]
     fn test_eval_simple() {
@@ -210,7 +655,7 @@ mod tests {
         assert_eq!(s.to_string(), "(+ 1 2)");
         let val = eval_expr(&s);
         match val {
-            Value::PosNumber(x) => assert_eq!(x, 3.0),
+            Value::Rational(x) => assert_eq!(x, Rational::from(3)),
             _ => panic!("Error")
         }
     }
@@ -268,6 +713,116 @@ mod tests {
         }
     }
     #[test]
+    fn test_eval_zero_mean() {
+        match eval("(0 ± 0.1) * (2 ± 0.1)") {
+            Value::Measurement(m) => assert_eq!(m, Measurement::new(0.0, 0.2)),
+            _ => panic!("Error")
+        }
+        match eval("1 / (-2 ± 0.1)") {
+            Value::Measurement(m) => assert_eq!(m, Measurement::new(-0.5, 0.025)),
+            _ => panic!("Error")
+        }
+    }
+    #[test]
+    fn test_labeled_uncertainties() {
+        let s = expr("12.3 ± 0.4 (stat) ± 0.2 (syst)");
+        assert_eq!(s.to_string(), "(± (± 12.3 0.4 stat) 0.2 syst)");
+        let s = expr("2 * 1 ± 0.4 (stat) + 1");
+        assert_eq!(s.to_string(), "(+ (* 2 (± 1 0.4 stat)) 1)");
+        match eval("12.3 ± 0.4 (stat) ± 0.2 (syst)") {
+            Value::Labeled(m) => assert_eq!(m, LabeledMeasurement::with_components(12.3, &[("stat", 0.4), ("syst", 0.2)])),
+            _ => panic!("Error")
+        }
+        match eval("(1 ± 0.3 (stat) ± 0.4 (syst)) * 2 + (1 ± 0.1 (stat))") {
+            Value::Labeled(m) => {
+                assert_eq!(m.component("stat"), Some((0.6f64 * 0.6 + 0.1 * 0.1).sqrt()));
+                assert_eq!(m.component("syst"), Some(0.8));
+                assert_eq!(m.to_string(), format!("3 ± {} (stat) ± 0.8 (syst)", m.component("stat").unwrap()));
+            },
+            _ => panic!("Error")
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn test_stray_label() {
+        eval("2 (stat)");
+    }
+    #[test]
+    fn test_samples() {
+        let s = expr("mean(9.81, 9.79, 9.83, 9.80) * 2");
+        assert_eq!(s.to_string(), "(* (mean 9.81 9.79 9.83 9.80) 2)");
+        let samples = [9.81, 9.79, 9.83, 9.80];
+        assert_eq!(Value::Measurement(Measurement::from_samples(&samples).unwrap()), eval("mean(9.81, 9.79, 9.83, 9.80)"));
+        assert_eq!(Value::Measurement(Measurement::from_samples(&samples).unwrap()), eval("mean([9.81, 9.79], [9.83, 9.80])"));
+        assert_eq!(Value::PosNumber(statistics::stdev(&samples)), eval("stdev([9.81, 9.79, 9.83, 9.80])"));
+        assert_eq!(Value::PosNumber(statistics::sem(&samples)), eval("sem(9.81, 9.79, 9.83, 9.80)"));
+        match eval("2 * mean(1, 2, 3) - 1") {
+            Value::Measurement(m) => assert_eq!(2.0, m.dof()),
+            v => panic!("not a measurement: {:?}", v),
+        }
+        //Welch–Satterthwaite: two equal contributions with 2 degrees of freedom each
+        match eval("mean(1, 2, 3) + mean(4, 5, 6)") {
+            Value::Measurement(m) => assert!((m.dof() - 4.0).abs() < 1e-12),
+            v => panic!("not a measurement: {:?}", v),
+        }
+    }
+    #[test]
+    fn test_distributions() {
+        assert_eq!(Value::Measurement(Measurement::rectangular(5.0, 0.02)), eval("rect(5.00, 0.02)"));
+        assert_eq!(Value::Measurement(Measurement::triangular(5.0, 0.02)), eval("tri(5.00, 0.02)"));
+        assert_eq!(Value::Measurement(Measurement::u_shaped(-5.0, 0.02)), eval("ushape(-5.00, 0.02)"));
+        let sum = Measurement::rectangular(5.0, 0.02) + Measurement::triangular(1.0, 0.06);
+        assert_eq!(Value::Measurement(sum), eval("rect(5, 0.02) + tri(1, 0.06)"));
+    }
+    #[test]
+    fn test_weighted_mean() {
+        let measurements = [Measurement::new(1.0, 0.1), Measurement::new(2.0, 0.2), Measurement::new(1.5, 0.1)];
+        let expected = Measurement::weighted_mean(&measurements).unwrap().mean;
+        match eval("wmean(1 ± 0.1, [2 ± 0.2, 1.5 ± 0.1])") {
+            Value::Measurement(m) => assert!(m.approx_eq(expected, F64Margin::default())),
+            v => panic!("not a measurement: {:?}", v),
+        }
+    }
+    #[test]
+    fn test_compare() {
+        let s = expr("a + 1 ~= b * 2");
+        assert_eq!(s.to_string(), "(~= (+ a 1) (* b 2))");
+        let expected = Measurement::new(9.79, 0.02).compare(&Measurement::new(9.80665, 0.0));
+        assert_eq!(Value::Comparison(expected.clone()), eval("compare(9.79 ± 0.02, 9.80665)"));
+        assert_eq!(Value::Comparison(expected.clone()), eval("9.79 ± 0.02 ~= 9.80665"));
+        assert_eq!(Value::Comparison(expected.with_sigmas(0.5)), eval("compare(9.79 ± 0.02, 9.80665, 0.5)"));
+        assert_eq!(Value::Comparison(Comparison::new(0.0, 0.0, f64::INFINITY)), eval("compare(2, 2)"));
+        match eval("2 ~= 3 ± 0") {
+            Value::Comparison(c) => assert_eq!((f64::NEG_INFINITY, 0.0), (c.z_score, c.p_value)),
+            v => panic!("not a comparison: {:?}", v),
+        }
+    }
+    #[test]
+    #[should_panic]
+    fn test_compare_in_arithmetic() {
+        eval("(1 ± 0.1 ~= 2) + 1");
+    }
+    #[test]
+    #[should_panic]
+    fn test_weighted_mean_of_exact_numbers() {
+        eval("wmean(1 ± 0.1, 2)");
+    }
+    #[test]
+    #[should_panic]
+    fn test_distribution_arity() {
+        eval("rect(5.00)");
+    }
+    #[test]
+    #[should_panic]
+    fn test_unknown_function() {
+        eval("median(1, 2, 3)");
+    }
+    #[test]
+    #[should_panic]
+    fn test_list_outside_function() {
+        eval("[1, 2] + 1");
+    }
+    #[test]
     fn test_valid_parenthesis() {
         let s = expr("(-1.0) ± 2.0");
         assert_eq!(s.to_string(), "(± (- 1.0) 2.0)")
@@ -277,4 +832,16 @@ mod tests {
     fn test_wrong_parenthesis() {
         expr("-1.0 (± 2.0");
     }
-}
\ No newline at end of file
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let s = expr("2 * x");
+        let json = serde_json::to_string(&s).unwrap();
+        assert_eq!(
+            r#"{"group":["mul",[{"atom":[{"pos_num":"2"},{"start":0,"end":1}]},{"atom":[{"ident":"x"},{"start":4,"end":5}]}],{"start":0,"end":5}]}"#,
+            json
+        );
+        let s = expr("mean([1.50, 2], 3) ± 0.1 (syst) ~= -e");
+        assert_eq!(s, serde_json::from_str(&serde_json::to_string(&s).unwrap()).unwrap());
+    }
+}
@@ -0,0 +1,695 @@
+use crate::measurement::{LabeledMeasurement, Measurement};
+use crate::montecarlo::{Distribution, Rng};
+use crate::statistics;
+use crate::value::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+///Order of the Taylor expansion used to propagate uncertainties.
+///
+///With `First` order(the default), the uncertainties are added in quadrature,
+///weighted by the partial derivatives of the expression.
+///
+///With `Second` order, the Hessian terms are also taken into account. They shift
+///the mean and increase the variance of non-linear functions, which matters for
+///functions such as `x^2` near zero or `1/x`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Order {
+    #[default]
+    First,
+    Second,
+}
+
+///A function of the input measurements, expanded up to second order
+///around their mean values.
+///
+///Only the non-zero partial derivatives are stored, indexed by the position
+///of the input measurement. The hessian is stored in full, i.e. both
+///(i, j) and (j, i) are present.
+#[derive(Debug, Clone)]
+pub(crate) struct Taylor {
+    value: f64,
+    gradient: BTreeMap<usize, f64>,
+    hessian: BTreeMap<(usize, usize), f64>,
+}
+
+impl Taylor {
+    fn constant(value: f64) -> Taylor {
+        Taylor {
+            value,
+            gradient: BTreeMap::new(),
+            hessian: BTreeMap::new(),
+        }
+    }
+
+    fn input(index: usize, value: f64) -> Taylor {
+        let mut gradient = BTreeMap::new();
+        gradient.insert(index, 1.0);
+        Taylor {
+            value,
+            gradient,
+            hessian: BTreeMap::new(),
+        }
+    }
+
+    ///Keeps only the derivatives with respect to the inputs for which `keep` is true
+    fn restrict<F: Fn(usize) -> bool>(&self, keep: F) -> Taylor {
+        Taylor {
+            value: self.value,
+            gradient: self.gradient.iter().filter(|(&i, _)| keep(i)).map(|(&i, &g)| (i, g)).collect(),
+            hessian: self
+                .hessian
+                .iter()
+                .filter(|(&(i, j), _)| keep(i) && keep(j))
+                .map(|(&k, &h)| (k, h))
+                .collect(),
+        }
+    }
+
+    fn is_constant(&self) -> bool {
+        self.gradient.is_empty() && self.hessian.is_empty()
+    }
+
+    ///Applies a scalar function 'f', given its value and its first
+    ///two derivatives evaluated at `self.value`
+    fn chain(self, f: f64, df: f64, d2f: f64) -> Taylor {
+        let mut hessian = combine(&self.hessian, df, &BTreeMap::new(), 0.0);
+        add_outer(&mut hessian, &self.gradient, &self.gradient, d2f);
+        Taylor {
+            value: f,
+            gradient: combine(&self.gradient, df, &BTreeMap::new(), 0.0),
+            hessian,
+        }
+    }
+
+    ///Applies a function of two variables 'f', given its value and its first
+    ///and second partial derivatives evaluated at `(x.value, y.value)`
+    #[allow(clippy::too_many_arguments)]
+    fn chain2(x: Taylor, y: Taylor, f: f64, fx: f64, fy: f64, fxx: f64, fxy: f64, fyy: f64) -> Taylor {
+        let mut hessian = combine(&x.hessian, fx, &y.hessian, fy);
+        add_outer(&mut hessian, &x.gradient, &x.gradient, fxx);
+        add_outer(&mut hessian, &x.gradient, &y.gradient, fxy);
+        add_outer(&mut hessian, &y.gradient, &x.gradient, fxy);
+        add_outer(&mut hessian, &y.gradient, &y.gradient, fyy);
+        Taylor {
+            value: f,
+            gradient: combine(&x.gradient, fx, &y.gradient, fy),
+            hessian,
+        }
+    }
+
+    fn exp(self) -> Taylor {
+        let y = self.value.exp();
+        self.chain(y, y, y)
+    }
+
+    fn ln(self) -> Taylor {
+        let x = self.value;
+        self.chain(x.ln(), 1.0 / x, -1.0 / (x * x))
+    }
+
+    fn powf(self, n: f64) -> Taylor {
+        let x = self.value;
+        self.chain(
+            x.powf(n),
+            n * x.powf(n - 1.0),
+            n * (n - 1.0) * x.powf(n - 2.0),
+        )
+    }
+
+    fn pow(self, other: Taylor) -> Taylor {
+        if other.is_constant() {
+            self.powf(other.value)
+        } else if self.is_constant() {
+            let y = self.value.powf(other.value);
+            let log = self.value.ln();
+            other.chain(y, log * y, log * log * y)
+        } else {
+            (other * self.ln()).exp()
+        }
+    }
+}
+
+impl Neg for Taylor {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        let x = self.value;
+        self.chain(-x, -1.0, 0.0)
+    }
+}
+
+impl Add for Taylor {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Taylor {
+            value: self.value + other.value,
+            gradient: combine(&self.gradient, 1.0, &other.gradient, 1.0),
+            hessian: combine(&self.hessian, 1.0, &other.hessian, 1.0),
+        }
+    }
+}
+
+impl Sub for Taylor {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Taylor {
+            value: self.value - other.value,
+            gradient: combine(&self.gradient, 1.0, &other.gradient, -1.0),
+            hessian: combine(&self.hessian, 1.0, &other.hessian, -1.0),
+        }
+    }
+}
+
+impl Mul for Taylor {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let (x, y) = (self.value, other.value);
+        Taylor::chain2(self, other, x * y, y, x, 0.0, 1.0, 0.0)
+    }
+}
+
+impl Div for Taylor {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        let (x, y) = (self.value, other.value);
+        Taylor::chain2(
+            self,
+            other,
+            x / y,
+            1.0 / y,
+            -x / (y * y),
+            0.0,
+            -1.0 / (y * y),
+            2.0 * x / (y * y * y),
+        )
+    }
+}
+
+///Computes the linear combination `kx * x + ky * y` of two sparse vectors
+fn combine<K: Ord + Copy>(
+    x: &BTreeMap<K, f64>,
+    kx: f64,
+    y: &BTreeMap<K, f64>,
+    ky: f64,
+) -> BTreeMap<K, f64> {
+    let mut result = BTreeMap::new();
+    for (&key, &val) in x {
+        *result.entry(key).or_insert(0.0) += kx * val;
+    }
+    for (&key, &val) in y {
+        *result.entry(key).or_insert(0.0) += ky * val;
+    }
+    result
+}
+
+///Adds `k * u * v^T` to the (sparse) matrix `m`
+fn add_outer(
+    m: &mut BTreeMap<(usize, usize), f64>,
+    u: &BTreeMap<usize, f64>,
+    v: &BTreeMap<usize, f64>,
+    k: f64,
+) {
+    if k == 0.0 {
+        return;
+    }
+    for (&i, &ui) in u {
+        for (&j, &vj) in v {
+            *m.entry((i, j)).or_insert(0.0) += k * ui * vj;
+        }
+    }
+}
+
+///The result of evaluating (part of) an expression.
+///
+///Exact numbers keep their own arithmetic, while anything that depends on a
+///measurement is expanded around the means of the measurements involved.
+#[derive(Debug, Clone)]
+pub(crate) enum Quantity {
+    Exact(Value), //Never holds a Value::Measurement or a Value::Labeled
+    Uncertain(Taylor),
+}
+
+impl Quantity {
+    fn into_taylor(self) -> Taylor {
+        match self {
+            Quantity::Exact(Value::PosNumber(x)) | Quantity::Exact(Value::Number(x)) => Taylor::constant(x),
+            Quantity::Exact(Value::Rational(x)) => Taylor::constant(x.to_f64()),
+            Quantity::Exact(Value::Measurement(m)) => panic!("measurement {} was not registered as an input", m),
+            Quantity::Exact(Value::Labeled(m)) => panic!("measurement {} was not registered as an input", m),
+            Quantity::Exact(Value::Comparison(c)) => panic!("a comparison can't be used in arithmetic: {}", c),
+            Quantity::Uncertain(t) => t,
+        }
+    }
+
+    pub(crate) fn pow(self, other: Quantity) -> Quantity {
+        match (self, other) {
+            (Quantity::Exact(x), Quantity::Exact(y)) => Quantity::Exact(x.pow(y)),
+            (x, y) => Quantity::Uncertain(x.into_taylor().pow(y.into_taylor())),
+        }
+    }
+}
+
+impl Neg for Quantity {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        match self {
+            Quantity::Exact(x) => Quantity::Exact(-x),
+            Quantity::Uncertain(x) => Quantity::Uncertain(-x),
+        }
+    }
+}
+
+impl Add for Quantity {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        match (self, other) {
+            (Quantity::Exact(x), Quantity::Exact(y)) => Quantity::Exact(x + y),
+            (x, y) => Quantity::Uncertain(x.into_taylor() + y.into_taylor()),
+        }
+    }
+}
+
+impl Sub for Quantity {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        match (self, other) {
+            (Quantity::Exact(x), Quantity::Exact(y)) => Quantity::Exact(x - y),
+            (x, y) => Quantity::Uncertain(x.into_taylor() - y.into_taylor()),
+        }
+    }
+}
+
+impl Mul for Quantity {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        match (self, other) {
+            (Quantity::Exact(x), Quantity::Exact(y)) => Quantity::Exact(x * y),
+            (x, y) => Quantity::Uncertain(x.into_taylor() * y.into_taylor()),
+        }
+    }
+}
+
+impl Div for Quantity {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        match (self, other) {
+            (Quantity::Exact(x), Quantity::Exact(y)) => Quantity::Exact(x / y),
+            (x, y) => Quantity::Uncertain(x.into_taylor() / y.into_taylor()),
+        }
+    }
+}
+
+///The measurements which the results of an evaluation depend on,
+///i.e. the variables of their Taylor expansions.
+///
+///Measurements are independent unless a correlation coefficient was set between them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Inputs {
+    sigmas: Vec<f64>,
+    labels: Vec<String>, //Label of the uncertainty component, empty if unlabeled
+    dofs: Vec<f64>,      //Degrees of freedom of each uncertainty
+    correlations: BTreeMap<(usize, usize), f64>, //Keyed by (i, j) with i < j
+    sampler: Option<Rng>, //In Monte Carlo mode, measurements are drawn at random instead
+}
+
+impl Inputs {
+    ///Inputs for one run of a Monte Carlo simulation: every measurement
+    ///registered is replaced by a random draw from its distribution
+    pub(crate) fn sampling(rng: Rng) -> Inputs {
+        Inputs {
+            sampler: Some(rng),
+            ..Inputs::default()
+        }
+    }
+
+    ///Whether the measurements are drawn at random, see `sampling`
+    pub(crate) fn is_sampling(&self) -> bool {
+        self.sampler.is_some()
+    }
+
+    ///Returns the random number generator of a Monte Carlo run
+    pub(crate) fn into_rng(self) -> Rng {
+        self.sampler.expect("the inputs are not sampled")
+    }
+
+    ///Registers a new measurement, independent of all the others.
+    ///Its uncertainty counts towards the component with the given label.
+    pub(crate) fn push(&mut self, mean: f64, sigma: f64, label: &str) -> Quantity {
+        if let Some(rng) = &mut self.sampler {
+            let draw = rng.sample(&Measurement::new(mean, sigma), Distribution::Normal);
+            return Quantity::Exact(Value::Number(draw));
+        }
+        self.sigmas.push(sigma);
+        self.labels.push(label.into());
+        self.dofs.push(f64::INFINITY);
+        Quantity::Uncertain(Taylor::input(self.sigmas.len() - 1, mean))
+    }
+
+    ///Registers a measurement, independent of all the others
+    pub(crate) fn push_measurement(&mut self, m: &Measurement) -> Quantity {
+        self.push_distribution(m, Distribution::Normal)
+    }
+
+    ///Same as `push_measurement`, but in Monte Carlo mode the
+    ///measurement is drawn from the given distribution
+    pub(crate) fn push_distribution(&mut self, m: &Measurement, distribution: Distribution) -> Quantity {
+        if let Some(rng) = &mut self.sampler {
+            return Quantity::Exact(Value::Number(rng.sample(m, distribution)));
+        }
+        let input = self.push(m.mean, m.sigma, "");
+        if let Some(dof) = self.dofs.last_mut() {
+            *dof = m.dof;
+        }
+        input
+    }
+
+    ///Registers a measurement with one independent input for each component of its uncertainty
+    pub(crate) fn push_labeled(&mut self, m: &LabeledMeasurement) -> Quantity {
+        if m.components().is_empty() {
+            return self.push_measurement(&m.combined());
+        }
+        let first = self.len();
+        let mut input = Quantity::Exact(Value::Number(m.mean()));
+        for (label, sigma) in m.components() {
+            input = input + self.push(0.0, *sigma, label);
+        }
+        for dof in &mut self.dofs[first..] {
+            *dof = m.dof();
+        }
+        input
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.sigmas.len()
+    }
+
+    pub(crate) fn sigma(&self, i: usize) -> f64 {
+        self.sigmas[i]
+    }
+
+    pub(crate) fn set_sigma(&mut self, i: usize, sigma: f64) {
+        self.sigmas[i] = sigma;
+    }
+
+    pub(crate) fn set_correlation(&mut self, i: usize, j: usize, rho: f64) {
+        let key = if i < j { (i, j) } else { (j, i) };
+        self.correlations.insert(key, rho);
+    }
+
+    ///Whether the correlation coefficients form a positive semi-definite matrix, as the
+    ///correlations of actual random variables do. Otherwise some variances are negative.
+    ///
+    ///Only the inputs which are correlated with another one are checked, with an LDLᵀ
+    ///decomposition whose pivots must not be negative.
+    pub(crate) fn is_positive_semidefinite(&self) -> bool {
+        const TOLERANCE: f64 = 1e-10;
+        let indices: Vec<usize> = self
+            .correlations
+            .keys()
+            .flat_map(|&(i, j)| vec![i, j])
+            .collect::<BTreeSet<usize>>()
+            .into_iter()
+            .collect();
+        let rho = |a: usize, b: usize| {
+            let (i, j) = (indices[a], indices[b]);
+            let key = if i < j { (i, j) } else { (j, i) };
+            if i == j {
+                1.0
+            } else {
+                self.correlations.get(&key).copied().unwrap_or(0.0)
+            }
+        };
+        let n = indices.len();
+        let mut l = vec![vec![0.0; n]; n];
+        let mut d = vec![0.0; n];
+        for j in 0..n {
+            let pivot = rho(j, j) - (0..j).map(|k| l[j][k] * l[j][k] * d[k]).sum::<f64>();
+            if pivot < -TOLERANCE {
+                return false;
+            }
+            d[j] = pivot.max(0.0);
+            for i in (j + 1)..n {
+                let x = rho(i, j) - (0..j).map(|k| l[i][k] * l[j][k] * d[k]).sum::<f64>();
+                if d[j] > TOLERANCE {
+                    l[i][j] = x / d[j];
+                } else if x.abs() > TOLERANCE {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    ///Element (i, j) of the covariance matrix of the inputs
+    fn covariance_entry(&self, i: usize, j: usize) -> f64 {
+        if i == j {
+            return self.sigmas[i] * self.sigmas[i];
+        }
+        let key = if i < j { (i, j) } else { (j, i) };
+        match self.correlations.get(&key) {
+            Some(rho) => rho * self.sigmas[i] * self.sigmas[j],
+            None => 0.0,
+        }
+    }
+
+    ///Returns the product `H Σ` of a (sparse) hessian and the covariance matrix, restricted
+    ///to the given indices. This is enough for the traces below, since the hessians
+    ///vanish outside of them.
+    fn hessian_times_covariance(&self, hessian: &BTreeMap<(usize, usize), f64>, indices: &[usize]) -> Vec<Vec<f64>> {
+        let n = indices.len();
+        let mut product = vec![vec![0.0; n]; n];
+        for (&(i, k), &h) in hessian {
+            let a = indices.binary_search(&i).unwrap();
+            for (b, &j) in indices.iter().enumerate() {
+                product[a][b] += h * self.covariance_entry(k, j);
+            }
+        }
+        product
+    }
+
+    ///Expected value of the expansion: `f + ½ tr(HΣ)` up to second order
+    fn mean_of(&self, x: &Taylor, order: Order) -> f64 {
+        let mut mean = x.value;
+        if order == Order::Second {
+            for (&(i, j), &h) in &x.hessian {
+                mean += 0.5 * h * self.covariance_entry(j, i);
+            }
+        }
+        mean
+    }
+
+    ///Covariance between two expansions: `gₓᵀ Σ gᵧ + ½ tr(Hₓ Σ Hᵧ Σ)` up to second order,
+    ///which is exact for normally distributed inputs
+    fn covariance_of(&self, x: &Taylor, y: &Taylor, order: Order) -> f64 {
+        let mut covariance = 0.0;
+        for (&i, &gx) in &x.gradient {
+            if let Some(&gy) = y.gradient.get(&i) {
+                covariance += (gx * self.sigmas[i]) * (gy * self.sigmas[i]);
+            }
+        }
+        for (&(i, j), &rho) in &self.correlations {
+            let gradient = |t: &Taylor, k: usize| t.gradient.get(&k).copied().unwrap_or(0.0);
+            let cross = gradient(x, i) * gradient(y, j) + gradient(x, j) * gradient(y, i);
+            covariance += cross * rho * self.sigmas[i] * self.sigmas[j];
+        }
+        if order == Order::Second {
+            let indices: Vec<usize> = x
+                .hessian
+                .keys()
+                .chain(y.hessian.keys())
+                .flat_map(|&(i, j)| vec![i, j])
+                .collect::<BTreeSet<usize>>()
+                .into_iter()
+                .collect();
+            let hx = self.hessian_times_covariance(&x.hessian, &indices);
+            let hy = self.hessian_times_covariance(&y.hessian, &indices);
+            for a in 0..indices.len() {
+                for b in 0..indices.len() {
+                    covariance += 0.5 * hx[a][b] * hy[b][a];
+                }
+            }
+        }
+        covariance
+    }
+
+    ///Expected value of an evaluated quantity
+    pub(crate) fn mean(&self, x: &Quantity, order: Order) -> f64 {
+        match x {
+            Quantity::Exact(Value::PosNumber(v)) | Quantity::Exact(Value::Number(v)) => *v,
+            Quantity::Exact(Value::Rational(v)) => v.to_f64(),
+            Quantity::Exact(Value::Measurement(m)) => m.mean,
+            Quantity::Exact(Value::Labeled(m)) => m.mean(),
+            Quantity::Exact(Value::Comparison(c)) => panic!("a comparison has no mean value: {}", c),
+            Quantity::Uncertain(t) => self.mean_of(t, order),
+        }
+    }
+
+    ///Covariance between two evaluated quantities. Exact numbers have no covariance.
+    pub(crate) fn covariance(&self, x: &Quantity, y: &Quantity, order: Order) -> f64 {
+        match (x, y) {
+            (Quantity::Uncertain(x), Quantity::Uncertain(y)) => self.covariance_of(x, y, order),
+            _ => 0.0,
+        }
+    }
+
+    ///Breaks the uncertainty of the expansion down into the contribution of the inputs
+    ///of each label. Empty if none of the inputs it depends on is labeled.
+    ///
+    ///Covariances between inputs with different labels only count towards the total.
+    fn components_of(&self, x: &Taylor, order: Order) -> Vec<(String, f64)> {
+        let mut labels: Vec<&str> = Vec::new();
+        let indices = x.gradient.keys().chain(x.hessian.keys().map(|(i, _)| i));
+        for &i in indices.collect::<BTreeSet<&usize>>() {
+            if !labels.contains(&self.labels[i].as_str()) {
+                labels.push(&self.labels[i]);
+            }
+        }
+        if labels.iter().all(|label| label.is_empty()) {
+            return Vec::new();
+        }
+        labels
+            .into_iter()
+            .map(|label| {
+                let part = x.restrict(|i| self.labels[i] == label);
+                (label.to_string(), self.covariance_of(&part, &part, order).sqrt())
+            })
+            .collect()
+    }
+
+    ///Effective degrees of freedom of the expansion, from the contribution of each input
+    ///to its uncertainty (Welch–Satterthwaite)
+    fn dof_of(&self, x: &Taylor, sigma: f64, order: Order) -> f64 {
+        let indices: BTreeSet<usize> = x.gradient.keys().chain(x.hessian.keys().map(|(i, _)| i)).copied().collect();
+        let contributions: Vec<(f64, f64)> = indices
+            .into_iter()
+            .filter(|&i| self.dofs[i].is_finite())
+            .map(|i| {
+                let part = x.restrict(|j| j == i);
+                (self.covariance_of(&part, &part, order).sqrt(), self.dofs[i])
+            })
+            .collect();
+        statistics::welch_satterthwaite(sigma, &contributions)
+    }
+
+    ///Collapses an evaluated quantity into its mean value and uncertainty
+    pub(crate) fn value(&self, x: Quantity, order: Order) -> Value {
+        match x {
+            Quantity::Exact(v) => v,
+            Quantity::Uncertain(t) => {
+                let mean = self.mean_of(&t, order);
+                let sigma = self.covariance_of(&t, &t, order).sqrt();
+                let components = self.components_of(&t, order);
+                let dof = self.dof_of(&t, sigma, order);
+                let measurement = Measurement { mean, sigma, dof };
+                Value::from(LabeledMeasurement { measurement, components })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::eval_with;
+    use float_cmp::{ApproxEq, F64Margin};
+
+    fn second_order(input: &str) -> Measurement {
+        match eval_with(input, Order::Second) {
+            Value::Measurement(m) => m,
+            _ => panic!("Error: {} is not a measurement", input),
+        }
+    }
+
+    fn assert_close(expected: Measurement, actual: Measurement) {
+        let margin = F64Margin { epsilon: 1e-12, ulps: 4 };
+        let message = format!("expected {}, got {}", expected, actual);
+        assert!(expected.approx_eq(actual, margin), "{}", message);
+    }
+
+    #[test]
+    fn test_square_near_zero() {
+        //For x ~ N(μ, σ²): E[x²] = μ² + σ² and Var[x²] = 4μ²σ² + 2σ⁴
+        assert_close(
+            Measurement::new(0.01, 2.0f64.sqrt() * 0.01),
+            second_order("(0 ± 0.1)^2"),
+        );
+        let (mu, sigma) = (3.0f64, 0.2f64);
+        assert_close(
+            Measurement::new(mu * mu + sigma * sigma, (4.0 * mu * mu * sigma * sigma + 2.0 * sigma.powi(4)).sqrt()),
+            second_order("(3 ± 0.2)^2"),
+        );
+    }
+
+    #[test]
+    fn test_reciprocal() {
+        //Second order expansion of 1/x: the mean shifts by σ²/μ³
+        //and the variance gains a 2σ⁴/μ⁶ term
+        let (mu, sigma) = (2.0f64, 0.1f64);
+        assert_close(
+            Measurement::new(
+                1.0 / mu + sigma * sigma / mu.powi(3),
+                (sigma * sigma / mu.powi(4) + 2.0 * sigma.powi(4) / mu.powi(6)).sqrt(),
+            ),
+            second_order("1 / (2 ± 0.1)"),
+        );
+    }
+
+    #[test]
+    fn test_product_is_exact() {
+        //For independent x and y: Var[xy] = μy²σx² + μx²σy² + σx²σy²
+        let variance: f64 = 9.0 * 0.01 + 4.0 * 0.04 + 0.01 * 0.04;
+        assert_close(
+            Measurement::new(6.0, variance.sqrt()),
+            second_order("(2 ± 0.1) * (3 ± 0.2)"),
+        );
+    }
+
+    #[test]
+    fn test_linear_matches_first_order() {
+        let input = "2 * (1.0 ± 0.01) - (1.7 ± 0.02) / 4";
+        let first = match eval_with(input, Order::First) {
+            Value::Measurement(m) => m,
+            _ => panic!("Error"),
+        };
+        assert_close(first, second_order(input));
+    }
+
+    #[test]
+    fn test_exact_numbers() {
+        match eval_with("2 ^ 3 - 1", Order::Second) {
+            Value::Rational(x) => assert_eq!(x.to_f64(), 7.0),
+            _ => panic!("Error"),
+        }
+    }
+
+    #[test]
+    fn test_correlated_inputs() {
+        //For correlated x and y: Cov(x + y, x - y) = σx² - σy²
+        //and, to second order, E[xy] = μxμy + ρσxσy
+        let mut inputs = Inputs::default();
+        let x = inputs.push(2.0, 0.3, "");
+        let y = inputs.push(3.0, 0.1, "");
+        inputs.set_correlation(0, 1, 0.5);
+
+        let sum = x.clone() + y.clone();
+        let difference = x.clone() - y.clone();
+        let covariance = inputs.covariance(&sum, &difference, Order::First);
+        assert!(covariance.approx_eq(0.09 - 0.01, F64Margin::default()));
+
+        let product = inputs.value(x * y, Order::Second);
+        match product {
+            Value::Measurement(m) => assert!(m.mean.approx_eq(6.0 + 0.5 * 0.3 * 0.1, F64Margin::default())),
+            _ => panic!("Error"),
+        }
+    }
+}
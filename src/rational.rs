@@ -0,0 +1,164 @@
+//!Exact fractions, for integer literals and their quotients
+//!
+//!Integers are rationals, and so are their sums, differences, products and quotients:
+//!`1/3 * 3` is exactly 1. The numerator and the denominator are 128-bit integers, and
+//!an operation whose result doesn't fit returns none, so that the caller can fall back
+//!to floating point.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+
+///A fraction of integers, always in lowest terms and with a positive denominator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Rational {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Rational {
+    ///The fraction `numerator / denominator`, in lowest terms.
+    ///
+    ///Panics if the denominator is zero, or if the fraction doesn't fit in 128-bit integers.
+    pub fn new(numerator: i128, denominator: i128) -> Rational {
+        match Rational::reduced(numerator, denominator) {
+            Some(x) => x,
+            None if denominator == 0 => panic!("the denominator of a fraction cannot be zero"),
+            None => panic!("the fraction {}/{} is too large", numerator, denominator),
+        }
+    }
+
+    ///Reduces the fraction to lowest terms, or returns none if the denominator is zero
+    fn reduced(numerator: i128, denominator: i128) -> Option<Rational> {
+        if denominator == 0 {
+            return None;
+        }
+        let negative = (numerator < 0) != (denominator < 0);
+        let divisor = gcd(numerator.unsigned_abs(), denominator.unsigned_abs());
+        let magnitude = i128::try_from(numerator.unsigned_abs() / divisor).ok()?;
+        let denominator = i128::try_from(denominator.unsigned_abs() / divisor).ok()?;
+        let numerator = if negative { -magnitude } else { magnitude };
+        Some(Rational { numerator, denominator })
+    }
+
+    pub fn numerator(&self) -> i128 {
+        self.numerator
+    }
+
+    ///Always positive
+    pub fn denominator(&self) -> i128 {
+        self.denominator
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.denominator == 1
+    }
+
+    ///The nearest float
+    pub fn to_f64(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    pub fn checked_neg(&self) -> Option<Rational> {
+        Some(Rational { numerator: self.numerator.checked_neg()?, ..*self })
+    }
+
+    pub fn checked_add(&self, other: &Rational) -> Option<Rational> {
+        let numerator = self
+            .numerator
+            .checked_mul(other.denominator)?
+            .checked_add(other.numerator.checked_mul(self.denominator)?)?;
+        Rational::reduced(numerator, self.denominator.checked_mul(other.denominator)?)
+    }
+
+    pub fn checked_sub(&self, other: &Rational) -> Option<Rational> {
+        self.checked_add(&other.checked_neg()?)
+    }
+
+    pub fn checked_mul(&self, other: &Rational) -> Option<Rational> {
+        //Cross-reducing first keeps the products small
+        let a = gcd(self.numerator.unsigned_abs(), other.denominator.unsigned_abs()) as i128;
+        let b = gcd(other.numerator.unsigned_abs(), self.denominator.unsigned_abs()) as i128;
+        let numerator = (self.numerator / a).checked_mul(other.numerator / b)?;
+        let denominator = (self.denominator / b).checked_mul(other.denominator / a)?;
+        Rational::reduced(numerator, denominator)
+    }
+
+    ///None if `other` is zero, or if the quotient doesn't fit
+    pub fn checked_div(&self, other: &Rational) -> Option<Rational> {
+        self.checked_mul(&Rational::reduced(other.denominator, other.numerator)?)
+    }
+
+    ///Raises the fraction to an integer power. None for a negative power of zero,
+    ///or if the result doesn't fit.
+    pub fn checked_pow(&self, n: i32) -> Option<Rational> {
+        let k = n.unsigned_abs();
+        let power = Rational::reduced(self.numerator.checked_pow(k)?, self.denominator.checked_pow(k)?)?;
+        if n < 0 {
+            Rational::reduced(power.denominator, power.numerator)
+        } else {
+            Some(power)
+        }
+    }
+}
+
+impl From<i128> for Rational {
+    fn from(n: i128) -> Rational {
+        Rational { numerator: n, denominator: 1 }
+    }
+}
+
+///Writes the fraction, e.g. `2/3` or `-1/4`, or just the numerator for an integer
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+///Greatest common divisor, which is 1 for two zeros so that it can always divide
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lowest_terms() {
+        assert_eq!(Rational::new(2, 3), Rational::new(-4, -6));
+        assert_eq!((-1, 4), (Rational::new(3, -12).numerator(), Rational::new(3, -12).denominator()));
+        assert_eq!(Rational::from(0), Rational::new(0, -5));
+        assert_eq!("-1/4", Rational::new(3, -12).to_string());
+        assert_eq!("7", Rational::new(14, 2).to_string());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let third = Rational::new(1, 3);
+        assert_eq!(Some(Rational::from(1)), third.checked_mul(&Rational::from(3)));
+        assert_eq!(Some(Rational::new(1, 6)), Rational::new(1, 2).checked_sub(&third));
+        assert_eq!(Some(Rational::new(5, 6)), Rational::new(1, 2).checked_add(&third));
+        assert_eq!(Some(Rational::new(3, 2)), Rational::new(1, 2).checked_div(&third));
+        assert_eq!(Some(Rational::new(9, 4)), Rational::new(2, 3).checked_pow(-2));
+        assert_eq!(None, third.checked_div(&Rational::from(0)));
+        assert_eq!(None, Rational::from(0).checked_pow(-1));
+        assert_eq!(None, Rational::from(i128::MAX).checked_add(&Rational::from(1)));
+        assert_eq!(None, Rational::from(10).checked_pow(40));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_denominator() {
+        Rational::new(1, 0);
+    }
+}
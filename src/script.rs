@@ -0,0 +1,113 @@
+//!Execution of scripts: sequences of assignments and expressions, one per line
+
+use crate::environment::Environment;
+use crate::error::{Error, Span};
+use crate::value::Value;
+use std::fmt;
+
+///Error raised by a line of a script
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptError {
+    pub line: usize, //line number, starting from 1
+    pub message: String,
+    pub span: Option<Span>, //columns of the line where the error was found, starting from 0
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "line {}, column {}: {}", self.line, span.start + 1, self.message),
+            None => write!(f, "line {}: {}", self.line, self.message),
+        }
+    }
+}
+
+///Splits an assignment such as `R = V / I` into the name and the expression.
+///Returns no name if the text is just an expression, e.g. `a ~= b`.
+pub fn split_assignment(text: &str) -> (Option<&str>, &str) {
+    match text.split_once('=') {
+        Some((name, expression))
+            if !name.trim().is_empty() && name.trim().chars().all(|c| c.is_alphanumeric() || c == '_') =>
+        {
+            (Some(name.trim()), expression)
+        }
+        _ => (None, text),
+    }
+}
+
+///Runs a script in the environment, line by line. Everything after a `#` is a comment.
+///
+///A line is either an assignment, `name = expression`, which defines a variable
+///for the following lines, or an expression, whose value is passed to `print`
+///along with its text.
+///
+///Stops at the first line that fails, returning its error.
+pub fn run<F: FnMut(&str, &Value)>(environment: &mut Environment, source: &str, mut print: F) -> Result<(), ScriptError> {
+    for (i, line) in source.lines().enumerate() {
+        let statement = match line.split_once('#') {
+            Some((code, _comment)) => code.trim(),
+            None => line.trim(),
+        };
+        if statement.is_empty() {
+            continue;
+        }
+        let (name, expression) = split_assignment(statement);
+        let result = match name {
+            Some(name) => environment.try_assign(name, expression).map(|_| ()),
+            None => environment.try_eval(expression).map(|value| print(statement, &value)),
+        };
+        if let Err(Error { message, span }) = result {
+            //Where the expression starts in the line
+            let offset = line[..line.len() - line.trim_start().len()].chars().count()
+                + statement[..statement.len() - expression.len()].chars().count();
+            let span = span.map(|s| Span::new(s.start + offset, s.end + offset));
+            return Err(ScriptError { line: i + 1, message, span });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::Measurement;
+
+    #[test]
+    fn test_script() {
+        let source = "
+            # Ohm's law
+            V = 10 ± 0.1   # volts
+            I = 2 ± 0.02
+            R = V / I
+            R
+            R * 2
+        ";
+        let mut env = Environment::new();
+        let mut printed = Vec::new();
+        run(&mut env, source, |text, value| printed.push((text.to_string(), value.clone()))).unwrap();
+        let r = Measurement::new(10.0, 0.1) / Measurement::new(2.0, 0.02);
+        assert_eq!(vec![("R".to_string(), Value::Measurement(r)), ("R * 2".to_string(), Value::Measurement(r * 2.0))], printed);
+        assert!(env.get("V").is_some());
+    }
+
+    #[test]
+    fn test_first_error() {
+        let mut env = Environment::new();
+        let mut printed = 0;
+        let error = run(&mut env, "x = 1\nx\ny + 1\nx", |_, _| printed += 1).unwrap_err();
+        assert_eq!(3, error.line);
+        assert_eq!("unknown variable: 'y'", error.message);
+        assert_eq!(Some(Span::new(0, 1)), error.span);
+        let error = run(&mut env, "  z = x + 1 +", |_, _| ()).unwrap_err();
+        assert_eq!(Some(Span::new(13, 13)), error.span);
+        assert_eq!("line 1, column 14: unexpected end of the expression", error.to_string());
+        assert_eq!(1, printed);
+    }
+
+    #[test]
+    fn test_split_assignment() {
+        assert_eq!((Some("R"), " V / I"), split_assignment("R = V / I"));
+        assert_eq!((None, "a ~= b"), split_assignment("a ~= b"));
+        assert_eq!((None, "2 * x"), split_assignment("2 * x"));
+    }
+}
@@ -0,0 +1,314 @@
+//!Significant-figure arithmetic, as taught in introductory labs
+//!
+//!Every number written in the expression is a measurement whose precision is given by its
+//!digits (see `DecimalNumber::sig_figs`). Products and quotients keep the fewest significant
+//!figures of their operands, sums and differences keep the coarsest decimal place, and
+//!powers keep the significant figures of their base. The digits are tracked alongside the
+//!usual evaluation without rounding the intermediate results: only the final result is
+//!rounded, and `evaluate` tells which number limited its precision.
+//!
+//!Constants such as `π` and exponents are exact, and don't limit the precision.
+
+use crate::arithmetic::{Decimal, Rounding};
+use crate::decimal::DecimalNumber;
+use crate::environment::Environment;
+use crate::error::{Error, Span};
+use crate::parser::Backend;
+use crate::token::Token;
+use crate::value::Value;
+use std::collections::HashMap;
+
+///The result of an expression rounded by the significant-figure rules
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rounded {
+    pub value: Value, //Before rounding
+    pub text: String, //Rounded, with its significant trailing zeros, e.g. "2.50"
+    pub sig_figs: Option<usize>, //None for exact results
+    pub explanation: String, //Which rule and which number limited the precision
+}
+
+///Which rule gave the precision of a number
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Rule {
+    Written, //A number as written in the expression
+    Product, //Fewest significant figures of the factors
+    Sum, //Coarsest decimal place of the terms
+    Zero, //Decimal place of a factor without nonzero digits, e.g. 0.0, which has no significant figures
+}
+
+///Precision of a measured number
+#[derive(Debug, Clone, PartialEq)]
+struct Digits {
+    sig_figs: i32,
+    place: i32, //Decimal place of the last significant figure, as in `Decimal::round_to`
+    rule: Rule,
+    limit: String, //The number written in the expression which limited the precision
+    ambiguous: bool, //Whether the trailing zeros of that number may be significant, see `DecimalNumber::is_ambiguous`
+}
+
+///A value with the precision of its digits, which is none for exact values
+#[derive(Debug, Clone)]
+struct Tracked {
+    value: Value,
+    digits: Option<Digits>,
+}
+
+///Evaluates an expression with significant-figure arithmetic, after the `definitions`
+///of its variables, and rounds the result with the given mode.
+///
+///For example, `2.50 * 1.2` is `3.0`: the product has 2 significant figures, limited by `1.2`.
+///```
+///use scicalc_rs::arithmetic::Rounding;
+///use scicalc_rs::sigfigs::evaluate;
+///
+///let result = evaluate(&[], "2.50 * 1.2", Rounding::HalfEven).unwrap();
+///assert_eq!("3.0", result.text);
+///assert_eq!(Some(2), result.sig_figs);
+///```
+pub fn evaluate(definitions: &[(&str, &str)], expression: &str, rounding: Rounding) -> Result<Rounded, Error> {
+    let mut environment = Environment::new();
+    let mut backend = Figures { variables: HashMap::new() };
+    for (name, input) in definitions {
+        let (_, tracked) = environment.try_assign_with(name, input, &backend)?;
+        backend.variables.insert(name.to_string(), tracked);
+    }
+    let (value, result) = environment.try_eval_with(expression, &backend)?;
+    let x = match value.number() {
+        Some(x) => x,
+        None => return Err(Error::unlocated("significant-figure arithmetic only applies to numbers")),
+    };
+    let digits = match result.digits {
+        Some(digits) => digits,
+        None => {
+            return Ok(Rounded {
+                text: value.to_string(),
+                value,
+                sig_figs: None,
+                explanation: "exact, as no measured number limits its precision".to_string(),
+            })
+        }
+    };
+    let decimal = Decimal::from_f64(x).ok_or_else(|| Error::unlocated("the result is not a finite number"))?;
+    let (text, explanation) = match digits.rule {
+        Rule::Written => (
+            decimal.to_places(digits.place, rounding),
+            match digits.sig_figs {
+                0 => format!("{}, as written in {}", place(digits.place), digits.limit),
+                n => format!("{}, as written in {}", figures(n), digits.limit),
+            },
+        ),
+        Rule::Product => (
+            decimal.to_sig_figs(digits.sig_figs.max(1) as usize, rounding),
+            format!("{}, the fewest of the factors, limited by {}", figures(digits.sig_figs), digits.limit),
+        ),
+        Rule::Sum => (
+            decimal.to_places(digits.place, rounding),
+            format!("{}, the coarsest of the terms, limited by {}", place(digits.place), digits.limit),
+        ),
+        Rule::Zero => (
+            decimal.to_places(digits.place, rounding),
+            format!("{}, as the factor {} has no significant figures", place(digits.place), digits.limit),
+        ),
+    };
+    let explanation = if digits.ambiguous {
+        format!("{}, assuming that its trailing zeros are not significant", explanation)
+    } else {
+        explanation
+    };
+    let rounded = decimal.round_to(digits.place, rounding);
+    let sig_figs = if rounded.is_zero() { 0 } else { (magnitude(rounded.to_f64()) + digits.place + 1).max(0) as usize };
+    Ok(Rounded {
+        value,
+        text,
+        sig_figs: Some(sig_figs),
+        explanation: format!("rounded to {}", explanation),
+    })
+}
+
+fn figures(n: i32) -> String {
+    match n {
+        1 => "1 significant figure".to_string(),
+        n => format!("{} significant figures", n),
+    }
+}
+
+fn place(place: i32) -> String {
+    match place {
+        1 => "1 decimal place".to_string(),
+        p if p > 1 => format!("{} decimal places", p),
+        0 => "the units".to_string(),
+        p => format!("the nearest {}", 10f64.powi(-p)),
+    }
+}
+
+///Power of ten of the leading digit, e.g. 2 for 123.4, and 0 for zero
+fn magnitude(x: f64) -> i32 {
+    let text = format!("{:e}", x);
+    text.split_once('e').and_then(|(_, exponent)| exponent.parse().ok()).unwrap_or(0)
+}
+
+///Significant-figure arithmetic as the backend of the evaluation
+struct Figures {
+    variables: HashMap<String, Tracked>, //precision of the definitions
+}
+
+impl Backend for Figures {
+    type Value = Tracked;
+
+    fn variable(&self, name: &str, span: Span) -> Result<Tracked, Error> {
+        match self.variables.get(name) {
+            Some(x) => Ok(x.clone()),
+            None => Err(Error::new(format!("unknown variable: \'{}\'", name), span)),
+        }
+    }
+
+    fn number(&self, x: &DecimalNumber, _: Span) -> Result<Tracked, Error> {
+        Ok(Tracked {
+            value: Value::PosNumber(x.as_float()),
+            digits: Some(Digits {
+                sig_figs: x.sig_figs() as i32,
+                place: x.decimal_place(),
+                rule: Rule::Written,
+                limit: x.to_string(),
+                ambiguous: x.is_ambiguous(),
+            }),
+        })
+    }
+
+    fn constant(&self, x: f64, _: Span) -> Result<Tracked, Error> {
+        Ok(exact(x))
+    }
+
+    fn operation(&self, op: &Token, operands: &[Tracked], span: Span) -> Result<Tracked, Error> {
+        match (op, operands) {
+            (Token::Add, [x, y]) => Ok(sum(x.value.clone() + y.value.clone(), x, y)),
+            (Token::Minus, [x, y]) => Ok(sum(x.value.clone() - y.value.clone(), x, y)),
+            (Token::Minus, [x]) => Ok(Tracked { value: -x.value.clone(), digits: x.digits.clone() }),
+            (Token::Mul, [x, y]) => Ok(product(x.value.clone() * y.value.clone(), x, y)),
+            (Token::Div, [x, y]) => Ok(product(x.value.clone() / y.value.clone(), x, y)),
+            //The exponent is exact, e.g. the 2 of a square
+            (Token::Caret, [x, y]) => {
+                let exponent = Tracked { value: y.value.clone(), digits: None };
+                Ok(product(x.value.clone().pow(y.value.clone()), x, &exponent))
+            }
+            _ => Err(Error::new(format!("bad sub-expressions of \'{}\'", op), span)),
+        }
+    }
+
+    fn function(&self, _: &str, _: &[Tracked], span: Span) -> Result<Tracked, Error> {
+        Err(unsupported(span))
+    }
+}
+
+fn exact(x: f64) -> Tracked {
+    Tracked { value: Value::Number(x), digits: None }
+}
+
+fn unsupported(span: Span) -> Error {
+    Error::new("significant-figure arithmetic only supports numbers, constants, +, -, *, / and ^", span)
+}
+
+///Sums and differences keep the coarsest decimal place of their terms
+fn sum(value: Value, x: &Tracked, y: &Tracked) -> Tracked {
+    let limit = match (&x.digits, &y.digits) {
+        (Some(a), Some(b)) if b.place < a.place => b,
+        (Some(a), _) => a,
+        (None, Some(b)) => b,
+        (None, None) => return Tracked { value, digits: None },
+    };
+    let place = limit.place;
+    let sig_figs = value.number().map_or(0, |x| magnitude(x) + place + 1);
+    let digits = Digits { sig_figs, place, rule: Rule::Sum, limit: limit.limit.clone(), ambiguous: limit.ambiguous };
+    Tracked { value, digits: Some(digits) }
+}
+
+///Products, quotients and powers keep the fewest significant figures of their operands.
+///A factor without nonzero digits, such as 0.0, keeps its decimal place instead.
+fn product(value: Value, x: &Tracked, y: &Tracked) -> Tracked {
+    let limit = match (&x.digits, &y.digits) {
+        (Some(a), Some(b)) if b.sig_figs < a.sig_figs => b,
+        (Some(a), _) => a,
+        (None, Some(b)) => b,
+        (None, None) => return Tracked { value, digits: None },
+    };
+    let sig_figs = limit.sig_figs;
+    let (place, rule) = match sig_figs {
+        0 => (limit.place, Rule::Zero),
+        _ => (value.number().map_or(0, |x| sig_figs - 1 - magnitude(x)), Rule::Product),
+    };
+    let digits = Digits { sig_figs, place, rule, limit: limit.limit.clone(), ambiguous: limit.ambiguous };
+    Tracked { value, digits: Some(digits) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rounded(expression: &str) -> Rounded {
+        evaluate(&[], expression, Rounding::HalfEven).unwrap()
+    }
+
+    #[test]
+    fn test_products() {
+        let result = rounded("2.50 * 1.2");
+        assert_eq!("3.0", result.text);
+        assert_eq!(Some(2), result.sig_figs);
+        assert_eq!("rounded to 2 significant figures, the fewest of the factors, limited by 1.2", result.explanation);
+        assert_eq!("0.0333", rounded("0.100 / 3.00").text);
+        assert_eq!("1000", rounded("12.0 * 100").text);
+        assert_eq!("6.2", rounded("2.50 ^ 2 * 1.0").text);
+        assert_eq!("6.25", rounded("2.50 ^ 2").text);
+        let result = rounded("0.0 / 2.0");
+        assert_eq!("0.0", result.text);
+        assert_eq!(Some(0), result.sig_figs);
+        assert_eq!("rounded to 1 decimal place, as the factor 0.0 has no significant figures", result.explanation);
+        assert_eq!("0.00", rounded("2.0 * 0.00 * 3.000").text);
+    }
+
+    #[test]
+    fn test_sums() {
+        let result = rounded("12.1 + 0.256");
+        assert_eq!("12.4", result.text);
+        assert_eq!(Some(3), result.sig_figs);
+        assert_eq!("rounded to 1 decimal place, the coarsest of the terms, limited by 12.1", result.explanation);
+        let result = rounded("78800 + 12.3");
+        assert_eq!("78800", result.text);
+        assert_eq!(
+            "rounded to the nearest 100, the coarsest of the terms, limited by 78800, assuming that its trailing zeros are not significant",
+            result.explanation
+        );
+        assert_eq!("78812", rounded("78800. + 12.3").text);
+        assert_eq!("78812", rounded("7.8800e4 + 12.3").text);
+        assert_eq!("the nearest 100", place(-2));
+        assert_eq!("0.0", rounded("1.2 - 1.19").text);
+        assert_eq!(Some(0), rounded("1.2 - 1.19").sig_figs);
+        assert_eq!("2.4", rounded("(1.23 + 1.2) * 1.000").text);
+    }
+
+    #[test]
+    fn test_exact_and_written() {
+        let result = rounded("pi");
+        assert_eq!(None, result.sig_figs);
+        assert_eq!("exact, as no measured number limits its precision", result.explanation);
+        let result = rounded("-2.50");
+        assert_eq!("-2.50", result.text);
+        assert_eq!("rounded to 3 significant figures, as written in 2.50", result.explanation);
+        assert_eq!("6.28", rounded("2.00 * pi * 1.00").text);
+        assert_eq!("rounded to 2 decimal places, as written in 0.00", rounded("0.00").explanation);
+        assert_eq!("3.0", evaluate(&[("r", "1.5")], "r * 2.00", Rounding::HalfEven).unwrap().text);
+    }
+
+    #[test]
+    fn test_rounding_modes() {
+        assert_eq!("0.12", rounded("0.125 * 1.0").text);
+        assert_eq!("0.13", evaluate(&[], "0.125 * 1.0", Rounding::HalfUp).unwrap().text);
+        assert_eq!("0.12", evaluate(&[], "0.129 * 1.0", Rounding::TowardZero).unwrap().text);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert!(evaluate(&[], "1.0 ± 0.1", Rounding::HalfEven).is_err());
+        assert!(evaluate(&[], "mean(1.0, 2.0)", Rounding::HalfEven).is_err());
+        assert!(evaluate(&[], "x * 2.0", Rounding::HalfEven).is_err());
+    }
+}
@@ -0,0 +1,244 @@
+//!Descriptive statistics of repeated readings, used for Type A evaluations of uncertainty
+
+///Arithmetic mean of the samples
+pub fn mean(samples: &[f64]) -> f64 {
+    if samples.is_empty() {
+        panic!("the mean of an empty list of samples is undefined");
+    }
+    samples.iter().sum::<f64>() / samples.len() as f64
+}
+
+///Sample standard deviation, i.e. with Bessel's correction (n - 1 in the denominator)
+pub fn stdev(samples: &[f64]) -> f64 {
+    if samples.len() < 2 {
+        panic!("the standard deviation needs at least two samples, got {}", samples.len());
+    }
+    let m = mean(samples);
+    let squares: f64 = samples.iter().map(|x| (x - m) * (x - m)).sum();
+    (squares / (samples.len() - 1) as f64).sqrt()
+}
+
+///Standard error of the mean, s/√n
+pub fn sem(samples: &[f64]) -> f64 {
+    stdev(samples) / (samples.len() as f64).sqrt()
+}
+
+///Effective degrees of freedom of a combined standard uncertainty `sigma`, from the
+///contribution `(c_i u_i, ν_i)` of each independent input, using the Welch–Satterthwaite formula
+///
+///ν_eff = sigma⁴ / Σ (c_i u_i)⁴ / ν_i
+///
+///Inputs with infinite degrees of freedom do not contribute to the sum.
+pub fn welch_satterthwaite(sigma: f64, contributions: &[(f64, f64)]) -> f64 {
+    let sum: f64 = contributions
+        .iter()
+        .filter(|(_, dof)| dof.is_finite())
+        .map(|(u, dof)| u.powi(4) / dof)
+        .sum();
+    if sum == 0.0 {
+        f64::INFINITY
+    } else {
+        sigma.powi(4) / sum
+    }
+}
+
+///Coverage factor k of a two-sided interval with the given `confidence` (e.g. 0.95),
+///for an uncertainty with `dof` degrees of freedom.
+///
+///This is the quantile of Student's t distribution, or of the normal distribution
+///when the degrees of freedom are infinite.
+pub fn coverage_factor(confidence: f64, dof: f64) -> f64 {
+    if confidence.is_nan() || confidence <= 0.0 || confidence >= 1.0 {
+        panic!("the confidence level must be between 0 and 1, got {}", confidence);
+    }
+    if dof.is_nan() || dof <= 0.0 {
+        panic!("the degrees of freedom must be positive, got {}", dof);
+    }
+    //The coverage is increasing in k, so bisect it
+    let (mut low, mut high) = (0.0, 1.0);
+    while coverage(high, dof) < confidence {
+        high *= 2.0;
+    }
+    for _ in 0..200 {
+        let middle = 0.5 * (low + high);
+        if coverage(middle, dof) < confidence {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    0.5 * (low + high)
+}
+
+///Two-sided p-value of a statistic `t`, i.e. the probability of |T| ≥ |t| for
+///Student's t distribution with `dof` degrees of freedom (normal if infinite)
+pub fn two_sided_p_value(t: f64, dof: f64) -> f64 {
+    1.0 - coverage(t.abs(), dof)
+}
+
+///Probability of a chi-square at least as large as `chi_squared`, with `ndf` degrees of freedom.
+///
+///Undefined (NaN) without degrees of freedom, e.g. for the weighted mean of a single measurement.
+pub fn chi_squared_p_value(chi_squared: f64, ndf: usize) -> f64 {
+    if ndf == 0 {
+        return f64::NAN;
+    }
+    1.0 - lower_gamma(0.5 * ndf as f64, 0.5 * chi_squared)
+}
+
+///Probability that |T| ≤ k, for Student's t distribution (normal if the degrees of freedom are infinite)
+fn coverage(k: f64, dof: f64) -> f64 {
+    if dof.is_infinite() {
+        //P(|Z| ≤ k) = erf(k/√2) = P(1/2, k²/2)
+        lower_gamma(0.5, 0.5 * k * k)
+    } else {
+        1.0 - incomplete_beta(0.5 * dof, 0.5, dof / (dof + k * k))
+    }
+}
+
+///Natural logarithm of the gamma function (Lanczos approximation)
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let mut tmp = x + 5.5;
+    tmp -= (x + 0.5) * tmp.ln();
+    let mut series = 1.000000000190015;
+    for (i, c) in COEFFICIENTS.iter().enumerate() {
+        series += c / (x + 1.0 + i as f64);
+    }
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+///Regularized lower incomplete gamma function P(a, x)
+fn lower_gamma(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let prefactor = (-x + a * x.ln() - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        //Series representation
+        let (mut term, mut sum, mut n) = (1.0 / a, 1.0 / a, a);
+        for _ in 0..1000 {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+            if term.abs() < sum.abs() * 1e-16 {
+                break;
+            }
+        }
+        sum * prefactor
+    } else {
+        //Continued fraction for the upper function Q(a, x) = 1 - P(a, x)
+        1.0 - prefactor * continued_fraction(|m| {
+            let m = m as f64;
+            (-m * (m - a), x + 2.0 * m + 1.0 - a)
+        }, x + 1.0 - a)
+    }
+}
+
+///Regularized incomplete beta function I_x(a, b)
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let prefactor = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        prefactor * beta_fraction(a, b, x) / a
+    } else {
+        1.0 - prefactor * beta_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+///Continued fraction of the incomplete beta function
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    continued_fraction(|m| {
+        //Even and odd coefficients alternate, with m counting from 1
+        let k = (m / 2) as f64;
+        let numerator = if m % 2 == 0 {
+            k * (b - k) * x / ((a + 2.0 * k - 1.0) * (a + 2.0 * k))
+        } else {
+            -(a + k) * (a + b + k) * x / ((a + 2.0 * k) * (a + 2.0 * k + 1.0))
+        };
+        (numerator, 1.0)
+    }, 1.0)
+}
+
+///Evaluates 1 / (b0 + a1 / (b1 + a2 / (b2 + ...))) with the modified Lentz method,
+///where `terms(m)` returns (a_m, b_m)
+fn continued_fraction<F: Fn(usize) -> (f64, f64)>(terms: F, b0: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0 / TINY;
+    let mut d = 1.0 / if b0.abs() < TINY { TINY } else { b0 };
+    let mut result = d;
+    for m in 1..1000 {
+        let (a, b) = terms(m);
+        d = b + a * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = b + a / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = c * d;
+        result *= delta;
+        if (delta - 1.0).abs() < 1e-16 {
+            break;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use float_cmp::{ApproxEq, F64Margin};
+
+    #[test]
+    fn test_readings() {
+        let samples = [1.0, 2.0, 3.0, 4.0];
+        assert_eq!(2.5, mean(&samples));
+        //Squared deviations: 2.25 + 0.25 + 0.25 + 2.25 = 5
+        let deviation = (5.0f64 / 3.0).sqrt();
+        assert!(stdev(&samples).approx_eq(deviation, F64Margin::default()));
+        assert!(sem(&samples).approx_eq(deviation / 2.0, F64Margin::default()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_single_sample() {
+        stdev(&[1.0]);
+    }
+
+    #[test]
+    fn test_welch_satterthwaite() {
+        //Two equal contributions with 4 degrees of freedom each
+        let sigma = 2f64.sqrt();
+        assert!(welch_satterthwaite(sigma, &[(1.0, 4.0), (1.0, 4.0)]).approx_eq(8.0, F64Margin::default()));
+        assert_eq!(f64::INFINITY, welch_satterthwaite(1.0, &[(1.0, f64::INFINITY)]));
+    }
+
+    #[test]
+    fn test_coverage_factor() {
+        let close = |x: f64, y: f64| (x - y).abs() < 1e-5;
+        assert!(close(coverage_factor(0.95, f64::INFINITY), 1.959964));
+        assert!(close(coverage_factor(0.682689492, f64::INFINITY), 1.0));
+        assert!(close(coverage_factor(0.95, 1.0), 12.706205));
+        assert!(close(coverage_factor(0.95, 10.0), 2.228139));
+        assert!(close(coverage_factor(0.99, 3.0), 5.840909));
+        assert!(close(two_sided_p_value(-1.959964, f64::INFINITY), 0.05));
+        assert!(close(chi_squared_p_value(3.841459, 1), 0.05));
+        assert!(close(chi_squared_p_value(18.307038, 10), 0.05));
+        assert!(chi_squared_p_value(0.0, 0).is_nan());
+    }
+}
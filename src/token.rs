@@ -1,9 +1,17 @@
 use crate::decimal::DecimalNumber;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
+///A token of an expression.
+///
+///With the `serde` feature, tokens are serialized by their name in snake case,
+///e.g. `"plus_minus"`, and literals and names along with their text, e.g. `{"pos_num": "2.50"}`.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
 pub enum Token {
     PosNum(DecimalNumber), //Positive number literal
+    Ident(String),         //Name of a variable
 
     //Constants
     EulersNum, //Euler's number
@@ -17,10 +25,15 @@ pub enum Token {
     Minus, //'-'  NOTE: could be prefix 'minus' or the infix subtraction operator
     Mul,   //'*'
     Div,   //'/'
+    Caret, //'^', exponentiation
+    Approx, //'~=', compares two measurements
 
     //Grouping
     LeftParen,  // '('
     RightParen, // ')'
+    LeftBracket,  // '[', starts a list of samples
+    RightBracket, // ']'
+    Comma,        // ',', separates the arguments of a function and the items of a list
 
     //End-of-file
     Eof,
@@ -31,6 +44,7 @@ impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Token::PosNum(d) => write!(f, "{}", d),
+            Token::Ident(name) => write!(f, "{}", name),
             Token::EulersNum => write!(f, "e"),
             Token::Pi => write!(f, "π"),
             Token::PlusMinus => write!(f, "±"),
@@ -38,8 +52,13 @@ impl fmt::Display for Token {
             Token::Minus => write!(f, "-"),
             Token::Mul => write!(f, "*"),
             Token::Div => write!(f, "/"),
+            Token::Caret => write!(f, "^"),
+            Token::Approx => write!(f, "~="),
             Token::LeftParen => write!(f, "("),
             Token::RightParen => write!(f, ")"),
+            Token::LeftBracket => write!(f, "["),
+            Token::RightBracket => write!(f, "]"),
+            Token::Comma => write!(f, ","),
             Token::Eof => write!(f, "EOF"), //useful for debugging
         }
     }
@@ -1,10 +1,26 @@
-use crate::measurement::Measurement;
+use crate::error::Error;
+use crate::measurement::{Comparison, LabeledMeasurement, Measurement};
+use crate::rational::Rational;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 use std::fmt;
 use std::ops::{Add, Sub, Mul, Div, Neg};
+
+///The result of an expression.
+///
+///With the `serde` feature, a value is serialized with its kind and its content,
+///e.g. `{"kind": "number", "value": -2.5}` or `{"kind": "measurement", "value": {...}}` in JSON.
+///The kinds are `pos_number`, `number`, `rational`, `measurement`, `labeled` and `comparison`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(tag = "kind", content = "value", rename_all = "snake_case"))]
 pub enum Value {
     PosNumber(f64),
     Number(f64),
-    Measurement(Measurement)
+    Rational(Rational), //Exact fraction of integer literals, e.g. '1/3'
+    Measurement(Measurement),
+    Labeled(LabeledMeasurement), //Measurement with labeled components, e.g. '12.3 ± 0.4 (stat) ± 0.2 (syst)'
+    Comparison(Comparison), //Result of comparing two measurements, e.g. 'a ~= b'
 }
 
 impl fmt::Display for Value {
@@ -13,14 +29,115 @@ impl fmt::Display for Value {
             Value::PosNumber(x) | Value::Number(x) => {
                 write!(f, "{}", x)
             },
+            Value::Rational(x) => {
+                write!(f, "{}", x.to_f64())
+            },
             Value::Measurement(x) => {
-                write!(f, "{}", x)
+                fmt::Display::fmt(x, f)
+            },
+            Value::Labeled(x) => {
+                fmt::Display::fmt(x, f)
+            },
+            Value::Comparison(x) => {
+                fmt::Display::fmt(x, f)
             }
         }
         
     }
 }
 
+impl From<f64> for Value {
+    fn from(x: f64) -> Self {
+        Value::Number(x)
+    }
+}
+
+impl From<Rational> for Value {
+    fn from(x: Rational) -> Self {
+        Value::Rational(x)
+    }
+}
+
+impl From<Measurement> for Value {
+    fn from(x: Measurement) -> Self {
+        Value::Measurement(x)
+    }
+}
+
+///A measurement without labeled components is a plain `Measurement`
+impl From<LabeledMeasurement> for Value {
+    fn from(x: LabeledMeasurement) -> Self {
+        if x.components().is_empty() {
+            Value::Measurement(x.combined())
+        } else {
+            Value::Labeled(x)
+        }
+    }
+}
+
+impl From<Comparison> for Value {
+    fn from(x: Comparison) -> Self {
+        Value::Comparison(x)
+    }
+}
+
+///Numbers become exact measurements. Comparisons are not measurements.
+impl TryFrom<Value> for Measurement {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::PosNumber(x) | Value::Number(x) => Ok(Measurement::new(x, 0.0)),
+            Value::Rational(x) => Ok(Measurement::new(x.to_f64(), 0.0)),
+            Value::Measurement(m) => Ok(m),
+            Value::Labeled(m) => Ok(m.combined()),
+            Value::Comparison(c) => Err(Error::unlocated(format!("a comparison is not a measurement: {}", c))),
+        }
+    }
+}
+
+///Only numbers, and measurements without uncertainty, are plain numbers
+impl TryFrom<Value> for f64 {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::PosNumber(x) | Value::Number(x) => Ok(x),
+            Value::Rational(x) => Ok(x.to_f64()),
+            Value::Measurement(m) if m.sigma == 0.0 => Ok(m.mean),
+            Value::Measurement(m) => Err(Error::unlocated(format!("the value has an uncertainty: {}", m))),
+            Value::Labeled(m) => Err(Error::unlocated(format!("the value has an uncertainty: {}", m))),
+            Value::Comparison(c) => Err(Error::unlocated(format!("a comparison is not a number: {}", c))),
+        }
+    }
+}
+
+///Comparisons are not numbers, so they can't be operands of arithmetic
+fn not_a_number(x: Comparison) -> ! {
+    panic!("a comparison can't be used in arithmetic: {}", x)
+}
+
+///Measurements with labeled components are combined with each other as a `LabeledMeasurement`,
+///where numbers are exact measurements
+fn labeled(x: Value) -> LabeledMeasurement {
+    match x {
+        Value::PosNumber(x) | Value::Number(x) => LabeledMeasurement::from(Measurement::exact(x)),
+        Value::Rational(x) => LabeledMeasurement::from(Measurement::exact(x.to_f64())),
+        Value::Measurement(m) => LabeledMeasurement::from(m),
+        Value::Labeled(m) => m,
+        Value::Comparison(x) => not_a_number(x),
+    }
+}
+
+///The exact result of an operation on fractions, or its float approximation if it doesn't fit
+fn exact(x: Option<Rational>, approximation: f64) -> Value {
+    match x {
+        Some(x) => Value::Rational(x),
+        None if approximation >= 0.0 => Value::PosNumber(approximation),
+        None => Value::Number(approximation),
+    }
+}
+
 impl Neg for Value {
     type Output = Self;
 
@@ -28,7 +145,10 @@ impl Neg for Value {
         match self {
             Value::PosNumber(x) => Value::PosNumber(-x),
             Value::Number(x) => Value::Number(-x),
+            Value::Rational(x) => exact(x.checked_neg(), -x.to_f64()),
             Value::Measurement(x) => Value::Measurement(-x),
+            Value::Labeled(x) => Value::Labeled(-x),
+            Value::Comparison(x) => not_a_number(x),
         }
     }
 }
@@ -39,28 +159,49 @@ impl Add for Value {
 
     fn add(self, _rhs: Value) -> Value {
         match self {
+            x if x.is_labeled() || _rhs.is_labeled() => Value::from(labeled(x) + labeled(_rhs)),
             Value::PosNumber(x) => {
                 match _rhs {
                     Value::PosNumber(y) => Value::PosNumber(x+y),
                     Value::Number(y) => Value::Number(x+y),
+                    Value::Rational(y) => Value::PosNumber(x+y.to_f64()),
                     Value::Measurement(y) => Value::Measurement(y+x),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
                 }
             },
             Value::Number(x) => {
                 match _rhs {
                     Value::PosNumber(y) => Value::Number(x+y),
                     Value::Number(y) => Value::Number(x+y),
+                    Value::Rational(y) => Value::Number(x+y.to_f64()),
                     Value::Measurement(y) => Value::Measurement(y+x),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
+                }
+            },
+            Value::Rational(x) => {
+                match _rhs {
+                    Value::PosNumber(y) => Value::PosNumber(x.to_f64()+y),
+                    Value::Number(y) => Value::Number(x.to_f64()+y),
+                    Value::Rational(y) => exact(x.checked_add(&y), x.to_f64()+y.to_f64()),
+                    Value::Measurement(y) => Value::Measurement(y+x.to_f64()),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
                 }
             },
             Value::Measurement(x) => {
                 match _rhs {
                     Value::PosNumber(y) => Value::Measurement(x+y),
                     Value::Number(y) => Value::Measurement(x+y),
+                    Value::Rational(y) => Value::Measurement(x+y.to_f64()),
                     Value::Measurement(y) => Value::Measurement(x+y),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
                 }
-            }
-
+            },
+            Value::Comparison(x) => not_a_number(x),
+            Value::Labeled(_) => unreachable!(),
         }
     }
 }
@@ -70,28 +211,49 @@ impl Sub for Value {
 
     fn sub(self, _rhs: Value) -> Value {
         match self {
+            x if x.is_labeled() || _rhs.is_labeled() => Value::from(labeled(x) - labeled(_rhs)),
             Value::PosNumber(x) => {
                 match _rhs {
                     Value::PosNumber(y) => Value::PosNumber(x-y),
                     Value::Number(y) => Value::Number(x-y),
+                    Value::Rational(y) => Value::PosNumber(x-y.to_f64()),
                     Value::Measurement(y) => Value::Measurement(-y+x),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
                 }
             },
             Value::Number(x) => {
                 match _rhs {
                     Value::PosNumber(y) => Value::Number(x-y),
                     Value::Number(y) => Value::Number(x-y),
+                    Value::Rational(y) => Value::Number(x-y.to_f64()),
                     Value::Measurement(y) => Value::Measurement(-y+x),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
+                }
+            },
+            Value::Rational(x) => {
+                match _rhs {
+                    Value::PosNumber(y) => Value::PosNumber(x.to_f64()-y),
+                    Value::Number(y) => Value::Number(x.to_f64()-y),
+                    Value::Rational(y) => exact(x.checked_sub(&y), x.to_f64()-y.to_f64()),
+                    Value::Measurement(y) => Value::Measurement(-y+x.to_f64()),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
                 }
             },
             Value::Measurement(x) => {
                 match _rhs {
                     Value::PosNumber(y) => Value::Measurement(x-y),
                     Value::Number(y) => Value::Measurement(x-y),
+                    Value::Rational(y) => Value::Measurement(x-y.to_f64()),
                     Value::Measurement(y) => Value::Measurement(x-y),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
                 }
-            }
-
+            },
+            Value::Comparison(x) => not_a_number(x),
+            Value::Labeled(_) => unreachable!(),
         }
     }
 }
@@ -101,28 +263,49 @@ impl Mul for Value {
 
     fn mul(self, _rhs: Value) -> Value {
         match self {
+            x if x.is_labeled() || _rhs.is_labeled() => Value::from(labeled(x) * labeled(_rhs)),
             Value::PosNumber(x) => {
                 match _rhs {
                     Value::PosNumber(y) => Value::PosNumber(x*y),
                     Value::Number(y) => Value::Number(x*y),
+                    Value::Rational(y) => Value::PosNumber(x*y.to_f64()),
                     Value::Measurement(y) => Value::Measurement(y*x),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
                 }
             },
             Value::Number(x) => {
                 match _rhs {
                     Value::PosNumber(y) => Value::Number(x*y),
                     Value::Number(y) => Value::Number(x*y),
+                    Value::Rational(y) => Value::Number(x*y.to_f64()),
                     Value::Measurement(y) => Value::Measurement(y*x),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
+                }
+            },
+            Value::Rational(x) => {
+                match _rhs {
+                    Value::PosNumber(y) => Value::PosNumber(x.to_f64()*y),
+                    Value::Number(y) => Value::Number(x.to_f64()*y),
+                    Value::Rational(y) => exact(x.checked_mul(&y), x.to_f64()*y.to_f64()),
+                    Value::Measurement(y) => Value::Measurement(y*x.to_f64()),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
                 }
             },
             Value::Measurement(x) => {
                 match _rhs {
                     Value::PosNumber(y) => Value::Measurement(x*y),
                     Value::Number(y) => Value::Measurement(x*y),
+                    Value::Rational(y) => Value::Measurement(x*y.to_f64()),
                     Value::Measurement(y) => Value::Measurement(x*y),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
                 }
-            }
-
+            },
+            Value::Comparison(x) => not_a_number(x),
+            Value::Labeled(_) => unreachable!(),
         }
     }
 }
@@ -132,28 +315,148 @@ impl Div for Value {
 
     fn div(self, _rhs: Value) -> Value {
         match self {
+            x if x.is_labeled() || _rhs.is_labeled() => Value::from(labeled(x) / labeled(_rhs)),
             Value::PosNumber(x) => {
                 match _rhs {
                     Value::PosNumber(y) => Value::PosNumber(x/y),
                     Value::Number(y) => Value::Number(x/y),
+                    Value::Rational(y) => Value::PosNumber(x/y.to_f64()),
                     Value::Measurement(y) => Value::Measurement(Measurement::new(x, 0.0) /y),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
                 }
             },
             Value::Number(x) => {
                 match _rhs {
                     Value::PosNumber(y) => Value::Number(x/y),
                     Value::Number(y) => Value::Number(x/y),
+                    Value::Rational(y) => Value::Number(x/y.to_f64()),
                     Value::Measurement(y) => Value::Measurement(Measurement::new(x, 0.0)/y),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
+                }
+            },
+            Value::Rational(x) => {
+                match _rhs {
+                    Value::PosNumber(y) => Value::PosNumber(x.to_f64()/y),
+                    Value::Number(y) => Value::Number(x.to_f64()/y),
+                    Value::Rational(y) => exact(x.checked_div(&y), x.to_f64()/y.to_f64()),
+                    Value::Measurement(y) => Value::Measurement(Measurement::new(x.to_f64(), 0.0)/y),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
                 }
             },
             Value::Measurement(x) => {
                 match _rhs {
                     Value::PosNumber(y) => Value::Measurement(x/y),
                     Value::Number(y) => Value::Measurement(x/y),
+                    Value::Rational(y) => Value::Measurement(x/y.to_f64()),
                     Value::Measurement(y) => Value::Measurement(x/y),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
                 }
-            }
+            },
+            Value::Comparison(x) => not_a_number(x),
+            Value::Labeled(_) => unreachable!(),
+        }
+    }
+}
+impl Value {
+    ///Exponentiation, i.e. computes `self ^ rhs`. Fractions raised to an integer power stay exact.
+    pub fn pow(self, _rhs: Value) -> Value {
+        match self {
+            x if x.is_labeled() || _rhs.is_labeled() => match _rhs.number() {
+                Some(n) => Value::from(labeled(x).powf(n)),
+                None => Value::from(labeled(x).pow(labeled(_rhs))),
+            },
+            Value::PosNumber(x) => {
+                match _rhs {
+                    Value::PosNumber(y) | Value::Number(y) => Value::PosNumber(x.powf(y)),
+                    Value::Rational(y) => Value::PosNumber(x.powf(y.to_f64())),
+                    Value::Measurement(y) => Value::Measurement(Measurement::new(x, 0.0).pow(y)),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
+                }
+            },
+            Value::Number(x) => {
+                match _rhs {
+                    Value::PosNumber(y) | Value::Number(y) => Value::Number(x.powf(y)),
+                    Value::Rational(y) => Value::Number(x.powf(y.to_f64())),
+                    Value::Measurement(y) => Value::Measurement(Measurement::new(x, 0.0).pow(y)),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
+                }
+            },
+            Value::Rational(x) => {
+                match _rhs {
+                    Value::PosNumber(y) | Value::Number(y) => Value::PosNumber(x.to_f64().powf(y)),
+                    Value::Rational(y) => {
+                        let power = x.to_f64().powf(y.to_f64());
+                        match i32::try_from(y.numerator()) {
+                            Ok(n) if y.is_integer() => exact(x.checked_pow(n), power),
+                            _ => Value::PosNumber(power),
+                        }
+                    },
+                    Value::Measurement(y) => Value::Measurement(Measurement::new(x.to_f64(), 0.0).pow(y)),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
+                }
+            },
+            Value::Measurement(x) => {
+                match _rhs {
+                    Value::PosNumber(y) | Value::Number(y) => Value::Measurement(x.powf(y)),
+                    Value::Rational(y) => Value::Measurement(x.powf(y.to_f64())),
+                    Value::Measurement(y) => Value::Measurement(x.pow(y)),
+                    Value::Comparison(y) => not_a_number(y),
+                    Value::Labeled(_) => unreachable!(),
+                }
+            },
+            Value::Comparison(x) => not_a_number(x),
+            Value::Labeled(_) => unreachable!(),
+        }
+    }
+
+    fn is_labeled(&self) -> bool {
+        matches!(self, Value::Labeled(_))
+    }
 
+    ///The value of a number or a fraction, or none for measurements and comparisons
+    pub fn number(&self) -> Option<f64> {
+        match self {
+            Value::PosNumber(x) | Value::Number(x) => Some(*x),
+            Value::Rational(x) => Some(x.to_f64()),
+            Value::Measurement(_) | Value::Labeled(_) | Value::Comparison(_) => None,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::eval;
+
+    #[test]
+    fn test_try_from() {
+        assert_eq!(Measurement::new(1.2, 0.1), Measurement::try_from(eval("1.2 ± 0.1")).unwrap());
+        assert_eq!(Measurement::new(4.0, 0.0), Measurement::try_from(eval("2 * 2")).unwrap());
+        assert!(Measurement::try_from(eval("1 ± 0.1 ~= 1")).is_err());
+        assert_eq!(0.25, f64::try_from(eval("1 / 4")).unwrap());
+        assert_eq!(3.0, f64::try_from(Value::Measurement(Measurement::new(3.0, 0.0))).unwrap());
+        assert!(f64::try_from(eval("1.2 ± 0.1")).is_err());
+    }
+
+    #[test]
+    fn test_rational() {
+        assert_eq!(Value::Rational(Rational::from(1)), eval("1/3 * 3"));
+        assert_eq!(Value::Rational(Rational::new(2, 3)), eval("2/3"));
+        assert_eq!(Value::Rational(Rational::new(-1, 8)), eval("-(2 ^ -3)"));
+        assert_eq!(Value::Rational(Rational::new(5, 6)), eval("1/2 + 1/3"));
+        assert_eq!("0.6666666666666666", eval("2/3").to_string());
+        assert_eq!(Value::PosNumber(0.5), eval("1/4 * 2.0"));
+        assert_eq!(Value::PosNumber(2f64.sqrt()), eval("2 ^ (1/2)"));
+        assert_eq!(Value::PosNumber(f64::INFINITY), eval("1 / 0"));
+        assert_eq!(Value::Number(-1e40), eval("-(10 ^ 20) * 10 ^ 20"));
+        assert_eq!(Value::Number(f64::NEG_INFINITY), eval("-1 / 0"));
+        assert_eq!(Value::Measurement(Measurement::new(1.5, 0.25)), eval("(3 ± 0.5) / 2"));
+    }
+}